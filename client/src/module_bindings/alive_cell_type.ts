@@ -34,6 +34,9 @@ export type AliveCell = {
   x: number,
   y: number,
   playerId: number,
+  bornGeneration: bigint,
+  colorHex: string,
+  weight: number,
 };
 
 /**
@@ -46,9 +49,12 @@ export namespace AliveCell {
   */
   export function getTypeScriptAlgebraicType(): AlgebraicType {
     return AlgebraicType.createProductType([
-      new ProductTypeElement("x", AlgebraicType.createU32Type()),
-      new ProductTypeElement("y", AlgebraicType.createU32Type()),
+      new ProductTypeElement("x", AlgebraicType.createI32Type()),
+      new ProductTypeElement("y", AlgebraicType.createI32Type()),
       new ProductTypeElement("playerId", AlgebraicType.createU32Type()),
+      new ProductTypeElement("bornGeneration", AlgebraicType.createU64Type()),
+      new ProductTypeElement("colorHex", AlgebraicType.createStringType()),
+      new ProductTypeElement("weight", AlgebraicType.createU32Type()),
     ]);
   }
 
@@ -34,6 +34,11 @@ export type Player = {
   id: number,
   identity: Identity,
   colorHex: string,
+  joinedAt: Timestamp,
+  name: string,
+  online: boolean,
+  score: bigint,
+  energy: number,
 };
 
 /**
@@ -49,6 +54,11 @@ export namespace Player {
       new ProductTypeElement("id", AlgebraicType.createU32Type()),
       new ProductTypeElement("identity", AlgebraicType.createIdentityType()),
       new ProductTypeElement("colorHex", AlgebraicType.createStringType()),
+      new ProductTypeElement("joinedAt", AlgebraicType.createTimestampType()),
+      new ProductTypeElement("name", AlgebraicType.createStringType()),
+      new ProductTypeElement("online", AlgebraicType.createBoolType()),
+      new ProductTypeElement("score", AlgebraicType.createU64Type()),
+      new ProductTypeElement("energy", AlgebraicType.createU32Type()),
     ]);
   }
 
@@ -1,14 +1,207 @@
-use spacetimedb::{reducer, table, Identity, ReducerContext, ScheduleAt, SpacetimeType, Table, TimeDuration};
-use std::collections::HashMap;
+use spacetimedb::{log, reducer, table, Identity, ReducerContext, ScheduleAt, SpacetimeType, Table, TimeDuration, Timestamp};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::hash::{Hash, Hasher};
 
+mod rle;
+mod rng;
+
+use rng::Rng;
+
+// cells may live a little beyond the visible canvas so patterns don't break at the edge
 const MARGIN: i32 = 5;
-const MIN_X: i32 = -MARGIN;
-const MIN_Y: i32 = -MARGIN;
-const MAX_X: i32 = 192 + MARGIN;
-const MAX_Y: i32 = 108 + MARGIN;
+const DEFAULT_CANVAS_WIDTH: u32 = 192;
+const DEFAULT_CANVAS_HEIGHT: u32 = 108;
 
-#[derive(SpacetimeType)]
+// join toasts are only interesting for a short while
+const JOIN_EVENT_RETENTION_MICROS: i64 = 10 * 60 * 1_000_000;
+
+const MAX_SIGNATURE_CELLS: usize = 256;
+const MAX_LIBRARY_PATTERN_CELLS: usize = 1024;
+
+// stamps that are always available, a library pattern with the same name takes precedence
+const BUILTIN_PATTERNS: [(&str, &str); 5] = [
+    ("blinker", "3o!"),
+    ("glider", "bo$2bo$3o!"),
+    ("lwss", "bo2bo$o4b$o3bo$4o!"),
+    ("r_pentomino", "b2o$2ob$bo!"),
+    ("gosper_glider_gun", "24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$2o8bo3bob2o4bobo$10bo5bo7bo$11bo3bo$12b2o!"),
+];
+
+// how many cells an overpopulated cell scatters around itself in explosive mode
+const EXPLOSION_SCATTER_CELLS: usize = 3;
+
+const DEFAULT_TICK_INTERVAL_MS: u32 = 500;
+const MIN_TICK_INTERVAL_MS: u32 = 10;
+const MAX_TICK_INTERVAL_MS: u32 = 60 * 60 * 1000;
+
+// how many generations of tick_stats are kept around
+const TICK_STATS_RETENTION: u64 = 600;
+
+// upper bound on work done by a single tick reducer
+const MAX_GENERATIONS_PER_TICK: u32 = 100;
+
+// largest rectangle neighbor_counts_in_rect will dump
+const MAX_NEIGHBOR_DUMP_AREA: i64 = 64 * 64;
+
+// largest neighbourhood radius, and the fixed point scale that makes 1/d exact for every
+// distance up to it so weighted sums don't depend on the order cells are scanned in
+const MAX_NEIGHBORHOOD_RADIUS: u8 = 8;
+const WEIGHT_SCALE: u32 = 840;
+
+// starting value of the board hash and of config.hash_chain
+const FNV_OFFSET_BASIS: u64 = 0xCBF2_9CE4_8422_2325;
+
+// upper bound on a single simulate preview
+const MAX_SIMULATED_GENERATIONS: u32 = 500;
+
+#[derive(Clone, Copy)]
+enum Transform {
+    FlipH,
+    FlipV,
+    Rotate90,
+    Rotate180,
+}
+
+impl Transform {
+    fn parse(op: &str) -> Result<Self, String> {
+        match op {
+            "flip_h" => Ok(Transform::FlipH),
+            "flip_v" => Ok(Transform::FlipV),
+            "rotate90" => Ok(Transform::Rotate90),
+            "rotate180" => Ok(Transform::Rotate180),
+            _ => Err(format!("unknown transform: {}", op)),
+        }
+    }
+
+    // works on doubled coordinates so the center of the canvas doesn't have to be a whole cell
+    fn apply(self, bounds: &Bounds, x: i32, y: i32) -> (i32, i32) {
+        let cx2 = bounds.min_x + bounds.max_x;
+        let cy2 = bounds.min_y + bounds.max_y;
+        let (dx2, dy2) = (2 * x - cx2, 2 * y - cy2);
+        let (nx2, ny2) = match self {
+            Transform::FlipH => (-dx2, dy2),
+            Transform::FlipV => (dx2, -dy2),
+            // clockwise on screen, where y grows downwards
+            Transform::Rotate90 => (-dy2, dx2),
+            Transform::Rotate180 => (-dx2, -dy2),
+        };
+        ((nx2 + cx2).div_euclid(2), (ny2 + cy2).div_euclid(2))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum EdgeMode {
+    // cells outside of the canvas die, empty space beyond the edge counts as dead neighbours
+    Kill,
+    // the edge acts as a mirror, a cell next to the edge sees its own reflection as a neighbour
+    Reflect,
+}
+
+impl EdgeMode {
+    fn parse(mode: &str) -> Result<Self, String> {
+        match mode {
+            "kill" => Ok(EdgeMode::Kill),
+            "reflect" => Ok(EdgeMode::Reflect),
+            _ => Err(format!("unknown edge mode: {}", mode)),
+        }
+    }
+}
+
+fn reflect(v: i32, min: i32, max: i32) -> i32 {
+    if v < min {
+        2 * min - 1 - v
+    } else if v > max {
+        2 * max + 1 - v
+    } else {
+        v
+    }
+}
+
+// region borders can also stay open, neighbours on either side then see each other as usual
+fn parse_region_edge(mode: &str) -> Result<Option<EdgeMode>, String> {
+    match mode {
+        "open" => Ok(None),
+        _ => EdgeMode::parse(mode).map(Some),
+    }
+}
+
+// birth and survival neighbour counts as bitmasks, written like "B3/S23"
+#[derive(Clone, Copy)]
+struct Rule {
+    birth: u16,
+    survive: u16,
+}
+
+impl Rule {
+    const CONWAY: Rule = Rule { birth: 1 << 3, survive: 1 << 2 | 1 << 3 };
+
+    fn parse(rule: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid rule: {}, expected something like B3/S23", rule);
+        let (birth, survive) = rule.split_once('/').ok_or_else(invalid)?;
+        let counts = |part: &str, prefix: char| -> Result<u16, String> {
+            let digits = part.strip_prefix(prefix).or_else(|| part.strip_prefix(prefix.to_ascii_lowercase())).ok_or_else(invalid)?;
+            digits.chars().try_fold(0, |mask, c| match c.to_digit(10) {
+                Some(count) if count <= 8 => Ok(mask | 1 << count),
+                _ => Err(invalid()),
+            })
+        };
+        Ok(Rule { birth: counts(birth, 'B')?, survive: counts(survive, 'S')? })
+    }
+
+    fn births(&self, count: usize) -> bool {
+        count <= 8 && self.birth & 1 << count != 0
+    }
+
+    fn survives(&self, count: usize) -> bool {
+        count <= 8 && self.survive & 1 << count != 0
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum OwnerColorMode {
+    // cells carry no color of their own, clients paint them with the owner's current color
+    Live,
+    // cells remember the owner's color from when they were placed or born
+    Snapshot,
+    // cells carry the owner's current color, set_color rewrites all of the player's cells,
+    // so renderers can skip the join with players at the cost of heavier color changes
+    Synced,
+}
+
+impl OwnerColorMode {
+    fn parse(mode: &str) -> Result<Self, String> {
+        match mode {
+            "live" => Ok(OwnerColorMode::Live),
+            "snapshot" => Ok(OwnerColorMode::Snapshot),
+            "synced" => Ok(OwnerColorMode::Synced),
+            _ => Err(format!("unknown owner color mode: {}", mode)),
+        }
+    }
+
+    fn stores_color(self) -> bool {
+        self != OwnerColorMode::Live
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ColorMode {
+    // a born cell goes to the owner of most of its parents, as the rule counted them
+    Majority,
+    // a born cell goes to the player holding most of the 8 squares around it
+    Dominance,
+}
+
+impl ColorMode {
+    fn parse(mode: &str) -> Result<Self, String> {
+        match mode {
+            "majority" => Ok(ColorMode::Majority),
+            "dominance" => Ok(ColorMode::Dominance),
+            _ => Err(format!("unknown color mode: {}", mode)),
+        }
+    }
+}
+
+#[derive(SpacetimeType, Clone, Copy)]
 struct Cell {
     x: i32,
     y: i32,
@@ -33,18 +226,291 @@ impl Hash for Cell {
 struct Config {
     #[primary_key]
     id: u32,
+    width: u32,
+    height: u32,
+    // cells may live this far outside of the visible canvas, clients size their view from
+    // width, height and margin instead of hardcoding them
+    margin: i32,
     tick_interval_ms: u32,
+    generation: u64,
+    heatmap_tile_width: u32,
+    heatmap_tile_height: u32,
+    heatmap_interval_ticks: u32,
+    auto_prune_isolated: bool,
+    generations_per_tick: u32,
+    dynamic_ownership: bool,
+    spawn_protection_ms: u32,
+    edge_mode: String,
+    // 0 means no cap
+    max_total_cells: u32,
+    at_capacity: bool,
+    // surviving also needs at least one neighbour of the same owner
+    social_survival: bool,
+    // every drift_interval generations all cells move by (drift_x, drift_y)
+    drift_x: i32,
+    drift_y: i32,
+    drift_interval: u32,
+    // overpopulated cells (7+ neighbours) scatter new cells around instead of just dying
+    explosive: bool,
+    // seed for the randomized rules, mixed with the generation and coordinates so every replay matches
+    rng_seed: u64,
+    // 0 turns off tracking of the largest colony
+    colony_interval_ticks: u32,
+    // cells older than this many generations die, 0 lets them live forever
+    max_cell_age: u32,
+    // whether toggle can remove other players' cells or only the caller's own
+    toggle_removes_any: bool,
+    // "live", "snapshot" or "synced", see OwnerColorMode
+    owner_color_mode: String,
+    // decides who owns a newly born cell
+    color_mode: String,
+    // neighbours an invader needs before it can capture a cell, 0 flips on any majority
+    capture_threshold: u8,
+    // derived from tick_interval_ms and generations_per_tick so clients show the same speed label
+    generations_per_second: f32,
+    // "none" or a transform the board is kept symmetric under after every generation
+    enforce_symmetry: String,
+    // ticks keep firing while paused but don't advance the board
+    paused: bool,
+    // the first player to connect unpauses the board, enabling it with nobody online pauses it until then
+    autostart_on_join: bool,
+    // the board pauses once the last player disconnects
+    autopause_on_empty: bool,
+    // weight given to manually placed cells, 0 turns seed weighting off
+    seed_weight: u32,
+    // add on another player's square blends colors instead of stacking a second cell,
+    // the blend is stored on the cell until its owner's color is stamped again by set_color
+    // in synced mode, set_owner_color_mode or a capture
+    mix_on_overlap: bool,
+    // "placer" or "neutral", who owns a blended cell
+    overlap_owner: String,
+    // energy each placed cell costs, 0 makes placing free
+    placement_cost: u32,
+    // tick stops at this generation and unschedules itself, 0 runs forever
+    max_generation: u64,
+    // max_generation was reached, step still advances the board by hand
+    finished: bool,
+    // every generation folds the board hash into this, equal chains mean equal histories
+    hash_chain: u64,
+    // squares counted around a cell in each direction, 1 is the usual 3x3 neighbourhood
+    neighborhood_radius: u8,
+    // neighbours count 1/d where d is their ring (chebyshev distance), instead of 1 each
+    distance_weighting: bool,
+    // inclusive ranges the neighbour sum has to fall in, used instead of the B/S rules
+    // once the radius is above 1 or weighting is on
+    birth_min: f32,
+    birth_max: f32,
+    survive_min: f32,
+    survive_max: f32,
+    // when the board last reached `generation`, clients interpolate from real timings
+    // instead of assuming every tick lands exactly on tick_interval_ms
+    server_gen_timestamp_micros: i64,
+    // chance a born cell goes to a random player instead of the one the color mode picks
+    mutation_percent: u8,
+    // generations between score samples, 0 stops sampling
+    score_history_interval: u32,
+    // samples older than this many generations are dropped
+    score_history_retention: u32,
+    // majority vote smoothing pass after every generation, see smooth
+    smooth: bool,
+    // chance per generation that a live cell takes over the owner of a random live neighbour
+    contagion_percent: u8,
+    // B/S rule outside of regions, like "B3/S23"
+    rule: String,
+    // energy a player starts with, so a newcomer can place before having cells that earn any
+    starting_energy: u32,
 }
 
 #[table(
     name = alive_cells,
     index(name = coordinates, btree(columns = [x, y])),
+    index(name = by_player, btree(columns = [player_id])),
     public
 )]
+#[derive(Clone)]
 struct AliveCell {
     x: i32,
     y: i32,
-    player_id: u32
+    player_id: u32,
+    born_generation: u64,
+    // empty in the live owner color mode
+    color_hex: String,
+    // extra say in who owns the cells it helps give birth to, set on manual placement
+    // from config.seed_weight and lowered by one every generation
+    weight: u32,
+}
+
+// rectangles with their own rules, a cell follows the region its own coordinate is in
+// (lowest id wins where regions overlap) and config.rule with the canvas edge mode everywhere else.
+// a kill edge walls the region off in both directions, a reflect edge mirrors it back inwards
+#[table(name = regions, public)]
+struct Region {
+    #[primary_key]
+    #[auto_inc]
+    id: u32,
+    bounds: Bounds,
+    rule: String,
+    // "open", "kill" or "reflect"
+    edge_mode: String,
+}
+
+// static decoration drawn beneath the live cells, the simulation and placement never touch it
+#[table(name = backdrop, index(name = coordinates, btree(columns = [x, y])), public)]
+struct BackdropCell {
+    x: i32,
+    y: i32,
+    color_hex: String,
+}
+
+// squares that come back to life after every generation, like a gun that never runs out,
+// each emitted cell costs its owner placement_cost energy like a placed one
+#[table(name = sources, index(name = coordinates, btree(columns = [x, y])), public)]
+struct Source {
+    #[primary_key]
+    #[auto_inc]
+    id: u32,
+    x: i32,
+    y: i32,
+    player_id: u32,
+}
+
+#[table(name = heatmap, public)]
+struct HeatmapTile {
+    tile_x: i32,
+    tile_y: i32,
+    count: u32,
+}
+
+// biggest 4-connected group of live cells, single row with id 0
+#[table(name = largest_colony, public)]
+struct LargestColony {
+    #[primary_key]
+    id: u32,
+    size: u32,
+    // owner of most of the colony's cells
+    player_id: u32,
+    generation: u64,
+}
+
+#[table(name = tick_stats, public)]
+struct TickStat {
+    // last generation covered, a batched tick sums up all of its generations in one row
+    #[index(btree)]
+    generation: u64,
+    births: u32,
+    deaths: u32,
+    population: u32,
+}
+
+// every player's score sampled every score_history_interval generations
+#[table(name = score_samples, public)]
+struct ScoreSample {
+    #[index(btree)]
+    player_id: u32,
+    #[index(btree)]
+    generation: u64,
+    score: u64,
+}
+
+#[derive(SpacetimeType)]
+struct ScorePoint {
+    generation: u64,
+    score: u64,
+}
+
+#[table(name = score_history_results, public)]
+struct ScoreHistoryResult {
+    #[primary_key]
+    identity: Identity,
+    player_id: u32,
+    // oldest first
+    points: Vec<ScorePoint>,
+}
+
+// every setting that shapes the game in one stable shape, so settings panels and tooling
+// don't have to follow each new config column. see the matching config fields for meanings
+#[derive(SpacetimeType)]
+struct RuleSummary {
+    // canvas
+    width: u32,
+    height: u32,
+    margin: i32,
+    edge_mode: String,
+
+    // timing
+    tick_interval_ms: u32,
+    generations_per_tick: u32,
+    generations_per_second: f32,
+    max_generation: u64,
+
+    // how cells live and die
+    rule: String,
+    neighborhood_radius: u8,
+    distance_weighting: bool,
+    birth_min: f32,
+    birth_max: f32,
+    survive_min: f32,
+    survive_max: f32,
+    max_cell_age: u32,
+    social_survival: bool,
+    explosive: bool,
+    smooth: bool,
+    auto_prune_isolated: bool,
+    enforce_symmetry: String,
+    drift_x: i32,
+    drift_y: i32,
+    drift_interval: u32,
+    rng_seed: u64,
+
+    // who owns what
+    color_mode: String,
+    owner_color_mode: String,
+    dynamic_ownership: bool,
+    capture_threshold: u8,
+    spawn_protection_ms: u32,
+    seed_weight: u32,
+    mutation_percent: u8,
+    contagion_percent: u8,
+
+    // placement
+    max_total_cells: u32,
+    placement_cost: u32,
+    starting_energy: u32,
+    toggle_removes_any: bool,
+    mix_on_overlap: bool,
+    overlap_owner: String,
+}
+
+#[table(name = rules_results, public)]
+struct RulesResult {
+    #[primary_key]
+    identity: Identity,
+    rules: RuleSummary,
+}
+
+#[table(name = recent_stats_results, public)]
+struct RecentStatsResult {
+    #[primary_key]
+    identity: Identity,
+    // oldest first
+    stats: Vec<TickStat>,
+}
+
+#[table(name = step_results, public)]
+struct StepResult {
+    #[primary_key]
+    identity: Identity,
+    generation: u64,
+    births: Vec<Cell>,
+    deaths: Vec<Cell>,
+}
+
+// what a single generation changed
+struct GenerationChanges {
+    births: Vec<Cell>,
+    deaths: Vec<Cell>,
+    // births had to be skipped to stay under the population cap
+    at_capacity: bool,
 }
 
 #[table(name = tick_schedule, scheduled(tick))]
@@ -62,114 +528,2739 @@ struct Player {
     #[unique]
     identity: Identity,
     color_hex: String,
+    joined_at: Timestamp,
+    name: String,
+    online: bool,
+    // one point per live cell per generation
+    score: u64,
+    // earned like score, spent on placing cells when placement_cost is set
+    energy: u32,
+}
+
+#[derive(SpacetimeType)]
+struct PlayerInfo {
+    id: u32,
+    name: String,
+    color_hex: String,
+    online: bool,
+    live_cells: u32,
+    score: u64,
+}
+
+// reducers can't return values, so query reducers store their answer keyed by the caller,
+// clients pick it up with `SELECT * FROM <table> WHERE identity = '<own identity>'`
+#[table(name = roster_results, public)]
+struct RosterResult {
+    #[primary_key]
+    identity: Identity,
+    players: Vec<PlayerInfo>,
+}
+
+#[table(name = join_events, public)]
+struct JoinEvent {
+    #[primary_key]
+    #[auto_inc]
+    id: u64,
+    player_id: u32,
+    #[index(btree)]
+    joined_micros: i64,
+}
+
+#[derive(SpacetimeType)]
+struct Bounds {
+    min_x: i32,
+    min_y: i32,
+    max_x: i32,
+    max_y: i32,
+}
+
+impl Bounds {
+    // area where cells can live, the canvas plus the margin around it
+    fn canvas(config: &Config) -> Self {
+        Bounds {
+            min_x: -config.margin,
+            min_y: -config.margin,
+            max_x: config.width as i32 + config.margin,
+            max_y: config.height as i32 + config.margin,
+        }
+    }
+
+    fn contains(&self, x: i32, y: i32) -> bool {
+        (self.min_x..=self.max_x).contains(&x) && (self.min_y..=self.max_y).contains(&y)
+    }
+}
+
+fn canvas_preset(name: &str) -> Result<(u32, u32), String> {
+    match name {
+        "small" => Ok((64, 36)),
+        "medium" => Ok((DEFAULT_CANVAS_WIDTH, DEFAULT_CANVAS_HEIGHT)),
+        "large" => Ok((384, 216)),
+        _ => Err(format!("unknown canvas preset: {}", name)),
+    }
+}
+
+#[table(name = verify_schedule_results, public)]
+struct VerifyScheduleResult {
+    #[primary_key]
+    identity: Identity,
+    repaired: bool,
+}
+
+#[table(name = compact_board_results, public)]
+struct CompactBoardResult {
+    #[primary_key]
+    identity: Identity,
+    removed: u32,
+}
+
+#[table(name = claim_results, public)]
+struct ClaimResult {
+    #[primary_key]
+    identity: Identity,
+    claimed: u32,
+}
+
+#[table(name = add_if_empty_results, public)]
+struct AddIfEmptyResult {
+    #[primary_key]
+    identity: Identity,
+    placed: u32,
+    skipped: u32,
+}
+
+// placed is false when the toggle removed the cell that was there
+#[table(name = toggle_results, public)]
+struct ToggleResult {
+    #[primary_key]
+    identity: Identity,
+    x: i32,
+    y: i32,
+    placed: bool,
+}
+
+#[derive(SpacetimeType)]
+struct OwnerCount {
+    player_id: u32,
+    live_cells: u32,
+}
+
+#[table(name = ownership_breakdown_results, public)]
+struct OwnershipBreakdownResult {
+    #[primary_key]
+    identity: Identity,
+    // most live cells first
+    owners: Vec<OwnerCount>,
+}
+
+#[table(name = board_extent_results, public)]
+struct BoardExtentResult {
+    #[primary_key]
+    identity: Identity,
+    // none when the board is empty
+    bounds: Option<Bounds>,
+    // live cells over canvas area, cells in the margin count too so it can slightly exceed 1
+    fill_ratio: f32,
+}
+
+#[table(name = player_bounds_results, public)]
+struct PlayerBoundsResult {
+    #[primary_key]
+    identity: Identity,
+    player_id: u32,
+    // none when the player has no live cells
+    bounds: Option<Bounds>,
+}
+
+#[derive(SpacetimeType)]
+struct NeighborCount {
+    x: i32,
+    y: i32,
+    count: u8,
+}
+
+#[table(name = neighbor_count_results, public)]
+struct NeighborCountResult {
+    #[primary_key]
+    identity: Identity,
+    // every square of the rectangle, row by row
+    counts: Vec<NeighborCount>,
+}
+
+#[table(name = simulate_results, public)]
+struct SimulateResult {
+    #[primary_key]
+    identity: Identity,
+    // generation the preview ends on
+    generation: u64,
+    cells: Vec<AliveCell>,
+}
+
+#[table(name = cell_info_results, public)]
+struct CellInfoResult {
+    #[primary_key]
+    identity: Identity,
+    x: i32,
+    y: i32,
+    // none when the square is empty
+    cell: Option<AliveCell>,
+}
+
+#[table(name = player_patterns, public)]
+struct PlayerPattern {
+    #[primary_key]
+    player_id: u32,
+    rle: String,
+}
+
+// named stamps admins add at runtime
+#[table(name = pattern_library, public)]
+struct LibraryPattern {
+    #[primary_key]
+    name: String,
+    rle: String,
+}
+
+#[table(name = pattern_list_results, public)]
+struct PatternListResult {
+    #[primary_key]
+    identity: Identity,
+    // library and built-in names, sorted
+    names: Vec<String>,
+}
+
+// colors handed to new players in order, wrapping around
+#[table(name = palette, public)]
+struct PaletteColor {
+    #[primary_key]
+    position: u32,
+    color_hex: String,
+}
+
+const DEFAULT_PALETTE: [&str; 8] = [
+    "#E6194B", "#3CB44B", "#FFE119", "#4363D8", "#F58231", "#911EB4", "#46F0F0", "#F032E6",
+];
+
+#[table(name = admins)]
+struct Admin {
+    #[primary_key]
+    identity: Identity,
+}
+
+fn require_admin(ctx: &ReducerContext) -> Result<(), String> {
+    if ctx.db.admins().identity().find(ctx.sender).is_none() {
+        return Err("admin only".to_string());
+    }
+    Ok(())
 }
 
 #[reducer(init)]
-fn init(ctx: &ReducerContext) {
-    let default_tick_interval_ms = 500;
-    ctx.db.config().insert(Config {
-        id: 0,
-        tick_interval_ms: default_tick_interval_ms,
-    });
+fn init(ctx: &ReducerContext) -> Result<(), String> {
+    // whoever publishes the module administers it
+    ctx.db.admins().insert(Admin { identity: ctx.sender });
+
+    let loop_duration = schedule_duration(DEFAULT_TICK_INTERVAL_MS)?;
+    ctx.db.config().insert(default_config());
+
+    for (position, color_hex) in DEFAULT_PALETTE.iter().enumerate() {
+        ctx.db.palette().insert(PaletteColor { position: position as u32, color_hex: color_hex.to_string() });
+    }
 
-    let loop_duration: TimeDuration = TimeDuration::from_micros((default_tick_interval_ms * 1000) as i64);
     ctx.db.tick_schedule().insert(TickSchedule {
         scheduled_id: 0,
         scheduled_at: loop_duration.into()
     });
+    Ok(())
 }
 
-#[reducer(client_connected)]
-fn identity_connected(ctx: &ReducerContext) {
-    if ctx.db.players().identity().find(ctx.sender).is_none() {
-        ctx.db.players().insert(Player {
-            id: 0,
-            identity: ctx.sender,
-            color_hex: "#FFFFFF".to_string(),
-        });
+fn default_config() -> Config {
+    let tick_interval_ms = DEFAULT_TICK_INTERVAL_MS.clamp(MIN_TICK_INTERVAL_MS, MAX_TICK_INTERVAL_MS);
+    Config {
+        id: 0,
+        width: DEFAULT_CANVAS_WIDTH,
+        height: DEFAULT_CANVAS_HEIGHT,
+        margin: MARGIN,
+        tick_interval_ms,
+        generation: 0,
+        heatmap_tile_width: 16,
+        heatmap_tile_height: 9,
+        heatmap_interval_ticks: 10,
+        auto_prune_isolated: false,
+        generations_per_tick: 1,
+        dynamic_ownership: false,
+        spawn_protection_ms: 0,
+        edge_mode: "kill".to_string(),
+        max_total_cells: 50_000,
+        at_capacity: false,
+        social_survival: false,
+        drift_x: 0,
+        drift_y: 0,
+        drift_interval: 1,
+        explosive: false,
+        rng_seed: 0,
+        colony_interval_ticks: 10,
+        max_cell_age: 0,
+        toggle_removes_any: false,
+        owner_color_mode: "live".to_string(),
+        color_mode: "majority".to_string(),
+        capture_threshold: 0,
+        generations_per_second: generations_per_second(1, tick_interval_ms),
+        enforce_symmetry: "none".to_string(),
+        paused: false,
+        autostart_on_join: false,
+        autopause_on_empty: false,
+        seed_weight: 0,
+        mix_on_overlap: false,
+        overlap_owner: "placer".to_string(),
+        placement_cost: 0,
+        max_generation: 0,
+        finished: false,
+        hash_chain: FNV_OFFSET_BASIS,
+        neighborhood_radius: 1,
+        distance_weighting: false,
+        birth_min: 3.0,
+        birth_max: 3.0,
+        survive_min: 2.0,
+        survive_max: 3.0,
+        server_gen_timestamp_micros: 0,
+        mutation_percent: 0,
+        score_history_interval: 10,
+        score_history_retention: 10_000,
+        smooth: false,
+        contagion_percent: 0,
+        rule: "B3/S23".to_string(),
+        starting_energy: 100,
     }
 }
 
-#[reducer(client_disconnected)]
-fn identity_disconnected(_ctx: &ReducerContext) {
+// every config read goes through here so a deleted config row heals itself instead of panicking
+fn get_or_init_config(ctx: &ReducerContext) -> Config {
+    ctx.db.config().id().find(0).unwrap_or_else(|| {
+        log::warn!("config row was missing, recreating it with defaults");
+        ctx.db.config().insert(default_config())
+    })
 }
 
+// replaces the whole palette, an empty list makes new players white
 #[reducer]
-fn add(ctx: &ReducerContext, cells: Vec<Cell>) {
-    let player_id = ctx.db.players().identity().find(ctx.sender).unwrap().id;
-    for c in cells {
-        ctx.db.alive_cells().insert(AliveCell { x: c.x, y: c.y, player_id: player_id });
+fn set_palette(ctx: &ReducerContext, colors: Vec<String>) -> Result<(), String> {
+    require_admin(ctx)?;
+    let old: Vec<PaletteColor> = ctx.db.palette().iter().collect();
+    for color in old {
+        ctx.db.palette().delete(color);
+    }
+    for (position, color_hex) in colors.into_iter().enumerate() {
+        ctx.db.palette().insert(PaletteColor { position: position as u32, color_hex });
     }
+    Ok(())
 }
 
 #[reducer]
-fn set_color(ctx: &ReducerContext, color_hex: String) {
-    if let Some(p) = ctx.db.players().identity().find(ctx.sender) {
-        ctx.db.players().identity().update(Player {
-            id: p.id,
-            identity: p.identity,
-            color_hex: color_hex,
-        });
-    }
+fn add_palette_color(ctx: &ReducerContext, color_hex: String) -> Result<(), String> {
+    require_admin(ctx)?;
+    let position = ctx.db.palette().iter().map(|c| c.position + 1).max().unwrap_or(0);
+    ctx.db.palette().insert(PaletteColor { position, color_hex });
+    Ok(())
 }
 
-#[reducer]
-fn update_tick_interval(ctx: &ReducerContext, interval_ms: u32) {
-    let mut config = ctx.db.config().id().find(0).unwrap();
-    config.tick_interval_ms = interval_ms;
-    ctx.db.config().id().update(config);
+// the nth player gets the nth palette color
+fn next_palette_color(ctx: &ReducerContext) -> String {
+    let mut colors: Vec<PaletteColor> = ctx.db.palette().iter().collect();
+    if colors.is_empty() {
+        return "#FFFFFF".to_string();
+    }
+    colors.sort_by_key(|c| c.position);
+    let index = ctx.db.players().count() as usize % colors.len();
+    colors.swap_remove(index).color_hex
+}
 
-    let loop_duration: TimeDuration = TimeDuration::from_micros((interval_ms * 1000) as i64);
-    
-    let mut tick_schedule = ctx.db.tick_schedule().scheduled_id().find(0).unwrap();
-    tick_schedule.scheduled_at = loop_duration.into();
-    ctx.db.tick_schedule().scheduled_id().update(tick_schedule);
+// 0 is rejected since it would make the scheduler spin, anything else is clamped to a sane range
+fn schedule_duration(interval_ms: u32) -> Result<TimeDuration, String> {
+    if interval_ms == 0 {
+        return Err("tick interval must be greater than 0".to_string());
+    }
+    let interval_ms = interval_ms.clamp(MIN_TICK_INTERVAL_MS, MAX_TICK_INTERVAL_MS);
+    Ok(TimeDuration::from_micros(interval_ms as i64 * 1000))
 }
 
-#[reducer]
-fn tick(ctx: &ReducerContext, _arg: TickSchedule) -> Result<(), String> {
-    let mut neighbours_by_cell: HashMap<Cell, Vec<u32>> = HashMap::new();
+fn generations_per_second(generations_per_tick: u32, tick_interval_ms: u32) -> f32 {
+    generations_per_tick as f32 * 1000.0 / tick_interval_ms as f32
+}
 
-    for alive_cell in ctx.db.alive_cells().iter() {
-        for x in alive_cell.x - 1..=alive_cell.x + 1 {
-            for y in alive_cell.y - 1..=alive_cell.y + 1 {
-                // make sure to put empty vec as neighbours even for "self" cell before continuing to avoid cell being ignore in later logic
-                let neighbors = neighbours_by_cell.entry(Cell{x,y}).or_insert(Vec::new());
-                if x == alive_cell.x && y == alive_cell.y {
-                    continue;
-                }
-                neighbors.push(alive_cell.player_id);
-            }
-        }
+#[reducer(client_connected)]
+fn identity_connected(ctx: &ReducerContext) {
+    let config = get_or_init_config(ctx);
+    if config.paused && config.autostart_on_join {
+        pause(ctx, false);
     }
 
+    if let Some(p) = ctx.db.players().identity().find(ctx.sender) {
+        ctx.db.players().identity().update(Player { online: true, ..p });
+    } else {
+        let color_hex = next_palette_color(ctx);
+        let player = ctx.db.players().insert(Player {
+            id: 0,
+            identity: ctx.sender,
+            color_hex,
+            joined_at: ctx.timestamp,
+            name: String::new(),
+            online: true,
+            score: 0,
+            energy: config.starting_energy,
+        });
+
+        // only first joins are announced, reconnects already have a player row
+        let now_micros = ctx.timestamp.to_micros_since_unix_epoch();
+        ctx.db.join_events().joined_micros().delete(..now_micros - JOIN_EVENT_RETENTION_MICROS);
+        ctx.db.join_events().insert(JoinEvent {
+            id: 0,
+            player_id: player.id,
+            joined_micros: now_micros,
+        });
+    }
+}
+
+#[reducer(client_disconnected)]
+fn identity_disconnected(ctx: &ReducerContext) {
+    if let Some(p) = ctx.db.players().identity().find(ctx.sender) {
+        ctx.db.players().identity().update(Player { online: false, ..p });
+    }
+
+    let config = get_or_init_config(ctx);
+    if !config.paused && config.autopause_on_empty && !ctx.db.players().iter().any(|p| p.online) {
+        pause(ctx, true);
+    }
+}
+
+#[reducer]
+fn add(ctx: &ReducerContext, cells: Vec<Cell>) -> Result<(), String> {
+    let player = ctx.db.players().identity().find(ctx.sender).ok_or("not registered")?;
+    let player_id = player.id;
+    let config = get_or_init_config(ctx);
+    let mut inserts: Vec<Cell> = Vec::new();
+    let mut blends: Vec<AliveCell> = Vec::new();
+    if config.mix_on_overlap {
+        let protected_players = spawn_protected_players(ctx, &config);
+        let mut seen: HashSet<Cell> = HashSet::new();
+        for c in cells.into_iter().filter(|c| seen.insert(*c)) {
+            let existing = ctx.db.alive_cells().coordinates().filter((c.x, c.y)).next();
+            match overlap(existing.as_ref().map(|e| e.player_id), player_id, &protected_players) {
+                Overlap::Insert => inserts.push(c),
+                Overlap::Blend => blends.extend(existing),
+                Overlap::Skip => {}
+            }
+        }
+    } else {
+        inserts = cells;
+    }
+
+    // only new rows take room and cost energy, blending an existing cell is free
+    if !has_room_for(ctx, &config, inserts.len()) {
+        return Err("board is at capacity".to_string());
+    }
+    spend_energy(ctx, &config, player_id, inserts.len())?;
+    let color_hex = cell_color(ctx, &config, player_id);
+    for c in inserts {
+        ctx.db.alive_cells().insert(AliveCell { x: c.x, y: c.y, player_id, born_generation: config.generation, color_hex: color_hex.clone(), weight: config.seed_weight });
+    }
+    for existing in blends {
+        mix_cell(ctx, &config, existing, &player);
+    }
+    Ok(())
+}
+
+#[derive(Debug, PartialEq)]
+enum Overlap {
+    // a new row on the empty square
+    Insert,
+    // the existing cell takes on the blend
+    Blend,
+    // own cells and cells of spawn protected players are left alone
+    Skip,
+}
+
+// what add does to a square when mixing on overlap, given the owner of the cell already there
+fn overlap(existing_owner: Option<u32>, placer: u32, protected_players: &HashSet<u32>) -> Overlap {
+    match existing_owner {
+        None => Overlap::Insert,
+        Some(owner) if owner == placer || protected_players.contains(&owner) => Overlap::Skip,
+        Some(_) => Overlap::Blend,
+    }
+}
+
+// painting over another player's cell keeps the cell but blends the two colors,
+// ownership goes to the placer or to nobody depending on overlap_owner
+fn mix_cell(ctx: &ReducerContext, config: &Config, existing: AliveCell, placer: &Player) {
+    let existing_color = if existing.color_hex.is_empty() {
+        ctx.db.players().id().find(existing.player_id).map(|p| p.color_hex).unwrap_or_default()
+    } else {
+        existing.color_hex.clone()
+    };
+    let color_hex = blend_colors(&existing_color, &placer.color_hex);
+    let player_id = blend_owner(&config.overlap_owner, placer.id);
+    ctx.db.alive_cells().delete(existing.clone());
+    ctx.db.alive_cells().insert(AliveCell { player_id, color_hex, weight: 0, ..existing });
+}
+
+fn blend_owner(overlap_owner: &str, placer: u32) -> u32 {
+    if overlap_owner == "neutral" { 0 } else { placer }
+}
+
+// channel-wise average of two #RRGGBB colors rounded down, a color that doesn't parse
+// gives way to the other one
+fn blend_colors(a: &str, b: &str) -> String {
+    let parse = |hex: &str| -> Option<[u8; 3]> {
+        let digits = hex.strip_prefix('#')?;
+        if digits.len() != 6 {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(digits.get(i..i + 2)?, 16).ok();
+        Some([channel(0)?, channel(2)?, channel(4)?])
+    };
+    match (parse(a), parse(b)) {
+        (Some(first), Some(second)) => {
+            let [r, g, b] = [0, 1, 2].map(|i| ((first[i] as u16 + second[i] as u16) / 2) as u8);
+            format!("#{:02X}{:02X}{:02X}", r, g, b)
+        }
+        (Some(_), None) => a.to_string(),
+        _ => b.to_string(),
+    }
+}
+
+#[reducer]
+fn set_mix_on_overlap(ctx: &ReducerContext, enabled: bool, owner: String) -> Result<(), String> {
+    require_admin(ctx)?;
+    if owner != "placer" && owner != "neutral" {
+        return Err(format!("unknown overlap owner: {}, expected placer or neutral", owner));
+    }
+    let mut config = get_or_init_config(ctx);
+    config.mix_on_overlap = enabled;
+    config.overlap_owner = owner;
+    ctx.db.config().id().update(config);
+    Ok(())
+}
+
+// like add but never stacks onto an occupied square, for stamping behind existing structures.
+// squares taken by anyone, or listed twice, are skipped and counted in add_if_empty_results
+#[reducer]
+fn add_if_empty(ctx: &ReducerContext, cells: Vec<Cell>) -> Result<(), String> {
+    let player_id = ctx.db.players().identity().find(ctx.sender).ok_or("not registered")?.id;
+    let config = get_or_init_config(ctx);
+    let mut seen: HashSet<Cell> = HashSet::new();
+    let (empty, taken): (Vec<Cell>, Vec<Cell>) = cells.into_iter()
+        .partition(|c| seen.insert(*c) && ctx.db.alive_cells().coordinates().filter((c.x, c.y)).next().is_none());
+    if !has_room_for(ctx, &config, empty.len()) {
+        return Err("board is at capacity".to_string());
+    }
+    spend_energy(ctx, &config, player_id, empty.len())?;
+
+    let color_hex = cell_color(ctx, &config, player_id);
+    for c in &empty {
+        ctx.db.alive_cells().insert(AliveCell { x: c.x, y: c.y, player_id, born_generation: config.generation, color_hex: color_hex.clone(), weight: config.seed_weight });
+    }
+    ctx.db.add_if_empty_results().identity().delete(ctx.sender);
+    ctx.db.add_if_empty_results().insert(AddIfEmptyResult { identity: ctx.sender, placed: empty.len() as u32, skipped: taken.len() as u32 });
+    Ok(())
+}
+
+// takes over neutral cells touching one of the caller's cells. cells are claimed in the given
+// order, so a claimed cell makes the neutral cells next to it claimable too
+#[reducer]
+fn claim(ctx: &ReducerContext, cells: Vec<Cell>) -> Result<(), String> {
+    let player_id = ctx.db.players().identity().find(ctx.sender).ok_or("not registered")?.id;
+    let config = get_or_init_config(ctx);
+    let color_hex = cell_color(ctx, &config, player_id);
+    let owns_neighbour = |x: i32, y: i32| {
+        (x - 1..=x + 1).any(|nx| (y - 1..=y + 1).any(|ny| {
+            (nx, ny) != (x, y) && ctx.db.alive_cells().coordinates().filter((nx, ny)).any(|c| c.player_id == player_id)
+        }))
+    };
+
+    let mut claimed = 0;
+    for c in cells {
+        let Some(neutral) = ctx.db.alive_cells().coordinates().filter((c.x, c.y)).find(|c| c.player_id == 0) else {
+            continue;
+        };
+        if !owns_neighbour(c.x, c.y) {
+            continue;
+        }
+        ctx.db.alive_cells().delete(neutral.clone());
+        ctx.db.alive_cells().insert(AliveCell { player_id, color_hex: color_hex.clone(), ..neutral });
+        claimed += 1;
+    }
+
+    ctx.db.claim_results().identity().delete(ctx.sender);
+    ctx.db.claim_results().insert(ClaimResult { identity: ctx.sender, claimed });
+    Ok(())
+}
+
+// same as add but with parallel coordinate arrays, which encode smaller for big brush strokes
+#[reducer]
+fn add_packed(ctx: &ReducerContext, xs: Vec<i32>, ys: Vec<i32>) -> Result<(), String> {
+    if xs.len() != ys.len() {
+        return Err(format!("got {} x coordinates but {} y coordinates", xs.len(), ys.len()));
+    }
+    let cells = xs.into_iter().zip(ys).map(|(x, y)| Cell { x, y }).collect();
+    add(ctx, cells)
+}
+
+#[reducer]
+fn set_signature(ctx: &ReducerContext, rle: String) -> Result<(), String> {
+    let player_id = ctx.db.players().identity().find(ctx.sender).ok_or("not registered")?.id;
+    rle::parse(&rle, MAX_SIGNATURE_CELLS)?;
+    ctx.db.player_patterns().player_id().delete(player_id);
+    ctx.db.player_patterns().insert(PlayerPattern { player_id, rle });
+    Ok(())
+}
+
+// places the caller's signature pattern with its top left corner at x, y
+#[reducer]
+fn stamp_signature(ctx: &ReducerContext, x: i32, y: i32) -> Result<(), String> {
+    let player_id = ctx.db.players().identity().find(ctx.sender).ok_or("not registered")?.id;
+    let pattern = ctx.db.player_patterns().player_id().find(player_id).ok_or("no signature pattern saved")?;
+    stamp_rle(ctx, player_id, &pattern.rle, MAX_SIGNATURE_CELLS, x, y)
+}
+
+// places a named pattern from the library, or a built-in one, with its top left corner at x, y
+#[reducer]
+fn stamp_pattern(ctx: &ReducerContext, name: String, x: i32, y: i32) -> Result<(), String> {
+    let player_id = ctx.db.players().identity().find(ctx.sender).ok_or("not registered")?.id;
+    let rle = match ctx.db.pattern_library().name().find(&name) {
+        Some(pattern) => pattern.rle,
+        None => BUILTIN_PATTERNS.iter()
+            .find(|(builtin, _)| *builtin == name)
+            .map(|(_, rle)| rle.to_string())
+            .ok_or_else(|| format!("unknown pattern: {}", name))?,
+    };
+    stamp_rle(ctx, player_id, &rle, MAX_LIBRARY_PATTERN_CELLS, x, y)
+}
+
+#[reducer]
+fn save_library_pattern(ctx: &ReducerContext, name: String, rle: String) -> Result<(), String> {
+    require_admin(ctx)?;
+    if name.is_empty() {
+        return Err("pattern name can't be empty".to_string());
+    }
+    rle::parse(&rle, MAX_LIBRARY_PATTERN_CELLS)?;
+    ctx.db.pattern_library().name().delete(&name);
+    ctx.db.pattern_library().insert(LibraryPattern { name, rle });
+    Ok(())
+}
+
+#[reducer]
+fn list_patterns(ctx: &ReducerContext) {
+    let mut names: Vec<String> = ctx.db.pattern_library().iter().map(|p| p.name).collect();
+    names.extend(BUILTIN_PATTERNS.iter().map(|(name, _)| name.to_string()));
+    names.sort();
+    names.dedup();
+
+    ctx.db.pattern_list_results().identity().delete(ctx.sender);
+    ctx.db.pattern_list_results().insert(PatternListResult { identity: ctx.sender, names });
+}
+
+// parts falling off the canvas or onto occupied squares are skipped
+fn stamp_rle(ctx: &ReducerContext, player_id: u32, rle: &str, max_cells: usize, x: i32, y: i32) -> Result<(), String> {
+    let config = get_or_init_config(ctx);
+    let bounds = Bounds::canvas(&config);
+    let cells: Vec<(i32, i32)> = rle::parse(rle, max_cells)?
+        .into_iter()
+        .map(|(dx, dy)| (x.saturating_add(dx), y.saturating_add(dy)))
+        .filter(|&(x, y)| bounds.contains(x, y) && ctx.db.alive_cells().coordinates().filter((x, y)).next().is_none())
+        .collect();
+
+    if !has_room_for(ctx, &config, cells.len()) {
+        return Err("board is at capacity".to_string());
+    }
+    spend_energy(ctx, &config, player_id, cells.len())?;
+    let color_hex = cell_color(ctx, &config, player_id);
+    for (x, y) in cells {
+        ctx.db.alive_cells().insert(AliveCell { x, y, player_id, born_generation: config.generation, color_hex: color_hex.clone(), weight: config.seed_weight });
+    }
+    Ok(())
+}
+
+// replaces the whole backdrop, a square listed twice keeps its last color
+#[reducer]
+fn set_backdrop(ctx: &ReducerContext, cells: Vec<BackdropCell>) -> Result<(), String> {
+    require_admin(ctx)?;
+    clear_backdrop(ctx)?;
+    let mut by_square: BTreeMap<(i32, i32), BackdropCell> = BTreeMap::new();
+    for cell in cells {
+        by_square.insert((cell.x, cell.y), cell);
+    }
+    for cell in by_square.into_values() {
+        ctx.db.backdrop().insert(cell);
+    }
+    Ok(())
+}
+
+#[reducer]
+fn clear_backdrop(ctx: &ReducerContext) -> Result<(), String> {
+    require_admin(ctx)?;
+    let cells: Vec<BackdropCell> = ctx.db.backdrop().iter().collect();
+    for cell in cells {
+        ctx.db.backdrop().delete(cell);
+    }
+    Ok(())
+}
+
+#[reducer]
+fn add_source(ctx: &ReducerContext, x: i32, y: i32) -> Result<(), String> {
+    let player_id = ctx.db.players().identity().find(ctx.sender).ok_or("not registered")?.id;
+    let config = get_or_init_config(ctx);
+    if !Bounds::canvas(&config).contains(x, y) {
+        return Err("source is outside of the canvas".to_string());
+    }
+    if ctx.db.sources().coordinates().filter((x, y)).next().is_some() {
+        return Err("there already is a source on this square".to_string());
+    }
+    ctx.db.sources().insert(Source { id: 0, x, y, player_id });
+    Ok(())
+}
+
+// owners remove their own sources, admins can remove any
+#[reducer]
+fn remove_source(ctx: &ReducerContext, x: i32, y: i32) -> Result<(), String> {
+    let player_id = ctx.db.players().identity().find(ctx.sender).ok_or("not registered")?.id;
+    let source = ctx.db.sources().coordinates().filter((x, y)).next().ok_or("no source on this square")?;
+    if source.player_id != player_id && require_admin(ctx).is_err() {
+        return Err("source belongs to another player".to_string());
+    }
+    ctx.db.sources().id().delete(source.id);
+    Ok(())
+}
+
+// click-to-draw primitive: places an own cell on an empty square or removes the cell that's there
+#[reducer]
+fn toggle(ctx: &ReducerContext, x: i32, y: i32) -> Result<(), String> {
+    let player_id = ctx.db.players().identity().find(ctx.sender).ok_or("not registered")?.id;
+    let config = get_or_init_config(ctx);
+
+    if let Some(alive_cell) = ctx.db.alive_cells().coordinates().filter((x, y)).next() {
+        if !config.toggle_removes_any && alive_cell.player_id != player_id {
+            return Err("cell belongs to another player".to_string());
+        }
+        ctx.db.alive_cells().coordinates().delete((x, y));
+        write_toggle_result(ctx, x, y, false);
+        return Ok(());
+    }
+
+    if !has_room_for(ctx, &config, 1) {
+        return Err("board is at capacity".to_string());
+    }
+    spend_energy(ctx, &config, player_id, 1)?;
+    let color_hex = cell_color(ctx, &config, player_id);
+    ctx.db.alive_cells().insert(AliveCell { x, y, player_id, born_generation: config.generation, color_hex, weight: config.seed_weight });
+    write_toggle_result(ctx, x, y, true);
+    Ok(())
+}
+
+fn write_toggle_result(ctx: &ReducerContext, x: i32, y: i32, placed: bool) {
+    ctx.db.toggle_results().identity().delete(ctx.sender);
+    ctx.db.toggle_results().insert(ToggleResult { identity: ctx.sender, x, y, placed });
+}
+
+#[reducer]
+fn set_toggle_removes_any(ctx: &ReducerContext, enabled: bool) -> Result<(), String> {
+    require_admin(ctx)?;
+    let mut config = get_or_init_config(ctx);
+    config.toggle_removes_any = enabled;
+    ctx.db.config().id().update(config);
+    Ok(())
+}
+
+// color stored on a new cell of the given player
+fn cell_color(ctx: &ReducerContext, config: &Config, player_id: u32) -> String {
+    if !OwnerColorMode::parse(&config.owner_color_mode).is_ok_and(OwnerColorMode::stores_color) {
+        return String::new();
+    }
+    ctx.db.players().id().find(player_id).map(|p| p.color_hex).unwrap_or_default()
+}
+
+// switching modes restamps existing cells, live clears their colors and the other modes store the current ones
+#[reducer]
+fn set_owner_color_mode(ctx: &ReducerContext, mode: String) -> Result<(), String> {
+    require_admin(ctx)?;
+    OwnerColorMode::parse(&mode)?;
+    let mut config = get_or_init_config(ctx);
+    config.owner_color_mode = mode;
+    let config = ctx.db.config().id().update(config);
+
+    let alive_cells: Vec<AliveCell> = ctx.db.alive_cells().iter().collect();
+    for alive_cell in alive_cells {
+        let color_hex = cell_color(ctx, &config, alive_cell.player_id);
+        if color_hex != alive_cell.color_hex {
+            ctx.db.alive_cells().delete(alive_cell.clone());
+            ctx.db.alive_cells().insert(AliveCell { color_hex, ..alive_cell });
+        }
+    }
+    Ok(())
+}
+
+// placing costs placement_cost energy per cell, energy is earned by keeping cells alive
+// on top of the starting_energy every player joins with
+fn spend_energy(ctx: &ReducerContext, config: &Config, player_id: u32, cells: usize) -> Result<(), String> {
+    if config.placement_cost == 0 {
+        return Ok(());
+    }
+    let p = ctx.db.players().id().find(player_id).ok_or("not registered")?;
+    let cost = (cells as u64).saturating_mul(config.placement_cost as u64);
+    if cost > p.energy as u64 {
+        return Err(format!("placing {} cells costs {} energy but only {} is left", cells, cost, p.energy));
+    }
+    ctx.db.players().id().update(Player { energy: p.energy - cost as u32, ..p });
+    Ok(())
+}
+
+#[reducer]
+fn update_placement_cost(ctx: &ReducerContext, placement_cost: u32, starting_energy: u32) -> Result<(), String> {
+    require_admin(ctx)?;
+    let mut config = get_or_init_config(ctx);
+    config.placement_cost = placement_cost;
+    config.starting_energy = starting_energy;
+    ctx.db.config().id().update(config);
+    Ok(())
+}
+
+fn has_room_for(ctx: &ReducerContext, config: &Config, new_cells: usize) -> bool {
+    config.max_total_cells == 0 || ctx.db.alive_cells().count() + new_cells as u64 <= config.max_total_cells as u64
+}
+
+#[reducer]
+fn set_color(ctx: &ReducerContext, color_hex: String) -> Result<(), String> {
+    let p = ctx.db.players().identity().find(ctx.sender).ok_or("not registered")?;
+    let player = ctx.db.players().identity().update(Player { color_hex, ..p });
+
+    let config = get_or_init_config(ctx);
+    if OwnerColorMode::parse(&config.owner_color_mode) == Ok(OwnerColorMode::Synced) {
+        let cells: Vec<AliveCell> = ctx.db.alive_cells().by_player().filter(player.id).collect();
+        for alive_cell in cells {
+            ctx.db.alive_cells().delete(alive_cell.clone());
+            ctx.db.alive_cells().insert(AliveCell { color_hex: player.color_hex.clone(), ..alive_cell });
+        }
+    }
+    Ok(())
+}
+
+#[reducer]
+fn set_name(ctx: &ReducerContext, name: String) -> Result<(), String> {
+    let p = ctx.db.players().identity().find(ctx.sender).ok_or("not registered")?;
+    ctx.db.players().identity().update(Player { name, ..p });
+    Ok(())
+}
+
+// who's playing and how they're doing, best score first
+#[reducer]
+fn roster(ctx: &ReducerContext) {
+    let mut players: Vec<PlayerInfo> = ctx.db.players().iter()
+        .map(|p| PlayerInfo {
+            id: p.id,
+            live_cells: ctx.db.alive_cells().by_player().filter(p.id).count() as u32,
+            name: p.name,
+            color_hex: p.color_hex,
+            online: p.online,
+            score: p.score,
+        })
+        .collect();
+    players.sort_by_key(|info| (std::cmp::Reverse(info.score), info.id));
+
+    ctx.db.roster_results().identity().delete(ctx.sender);
+    ctx.db.roster_results().insert(RosterResult { identity: ctx.sender, players });
+}
+
+// bounding box of a player's colony, for "jump to my colony" in the client
+#[reducer]
+fn player_bounds(ctx: &ReducerContext, player_id: u32) {
+    let bounds = extent(ctx.db.alive_cells().by_player().filter(player_id));
+    ctx.db.player_bounds_results().identity().delete(ctx.sender);
+    ctx.db.player_bounds_results().insert(PlayerBoundsResult { identity: ctx.sender, player_id, bounds });
+}
+
+// live cells per owner over the whole board in one go, for pie charts
+#[reducer]
+fn ownership_breakdown(ctx: &ReducerContext) {
+    let mut counts: HashMap<u32, u32> = HashMap::new();
+    for alive_cell in ctx.db.alive_cells().iter() {
+        *counts.entry(alive_cell.player_id).or_insert(0) += 1;
+    }
+    let mut owners: Vec<OwnerCount> = counts.into_iter()
+        .map(|(player_id, live_cells)| OwnerCount { player_id, live_cells })
+        .collect();
+    owners.sort_by_key(|o| (std::cmp::Reverse(o.live_cells), o.player_id));
+
+    ctx.db.ownership_breakdown_results().identity().delete(ctx.sender);
+    ctx.db.ownership_breakdown_results().insert(OwnershipBreakdownResult { identity: ctx.sender, owners });
+}
+
+// occupied area of the whole board plus how full the canvas is, for auto-fitting the view
+#[reducer]
+fn board_extent(ctx: &ReducerContext) {
+    let config = get_or_init_config(ctx);
+    let bounds = extent(ctx.db.alive_cells().iter());
+    let area = config.width as f32 * config.height as f32;
+    let fill_ratio = if area > 0.0 { ctx.db.alive_cells().count() as f32 / area } else { 0.0 };
+
+    ctx.db.board_extent_results().identity().delete(ctx.sender);
+    ctx.db.board_extent_results().insert(BoardExtentResult { identity: ctx.sender, bounds, fill_ratio });
+}
+
+fn extent(cells: impl Iterator<Item = AliveCell>) -> Option<Bounds> {
+    let mut bounds: Option<Bounds> = None;
+    for alive_cell in cells {
+        let b = bounds.get_or_insert(Bounds { min_x: alive_cell.x, min_y: alive_cell.y, max_x: alive_cell.x, max_y: alive_cell.y });
+        b.min_x = b.min_x.min(alive_cell.x);
+        b.min_y = b.min_y.min(alive_cell.y);
+        b.max_x = b.max_x.max(alive_cell.x);
+        b.max_y = b.max_y.max(alive_cell.y);
+    }
+    bounds
+}
+
+#[reducer]
+fn cell_info(ctx: &ReducerContext, x: i32, y: i32) {
+    let cell = ctx.db.alive_cells().coordinates().filter((x, y)).next();
+    ctx.db.cell_info_results().identity().delete(ctx.sender);
+    ctx.db.cell_info_results().insert(CellInfoResult { identity: ctx.sender, x, y, cell });
+}
+
+#[reducer]
+fn update_tick_interval(ctx: &ReducerContext, interval_ms: u32) -> Result<(), String> {
+    let loop_duration = schedule_duration(interval_ms)?;
+
+    let mut config = get_or_init_config(ctx);
+    config.tick_interval_ms = (loop_duration.to_micros() / 1000) as u32;
+    config.generations_per_second = generations_per_second(config.generations_per_tick, config.tick_interval_ms);
+    let config = ctx.db.config().id().update(config);
+    ensure_schedule(ctx, &config);
+    Ok(())
+}
+
+fn tick_interval(config: &Config) -> TimeDuration {
+    TimeDuration::from_micros(config.tick_interval_ms as i64 * 1000)
+}
+
+// puts the repeating tick on the configured interval, recreating it if it went missing
+fn ensure_schedule(ctx: &ReducerContext, config: &Config) {
+    let scheduled_at: ScheduleAt = tick_interval(config).into();
+    match ctx.db.tick_schedule().scheduled_id().find(0) {
+        Some(tick_schedule) => {
+            ctx.db.tick_schedule().scheduled_id().update(TickSchedule { scheduled_at, ..tick_schedule });
+        }
+        None => {
+            ctx.db.tick_schedule().insert(TickSchedule { scheduled_id: 0, scheduled_at });
+        }
+    }
+}
+
+// repairs the tick schedule when it no longer matches config.tick_interval_ms,
+// a board that reached max_generation has no schedule on purpose
+#[reducer]
+fn verify_schedule(ctx: &ReducerContext) -> Result<(), String> {
+    require_admin(ctx)?;
+    let config = get_or_init_config(ctx);
+    let schedule = ctx.db.tick_schedule().scheduled_id().find(0);
+    let finished = config.finished && reached_max_generation(&config);
+    let in_sync = if finished {
+        schedule.is_none()
+    } else {
+        schedule.is_some_and(|s| matches!(s.scheduled_at, ScheduleAt::Interval(interval) if interval == tick_interval(&config)))
+    };
+    if !in_sync && finished {
+        log::warn!("finished board is still scheduled, unscheduling it");
+        ctx.db.tick_schedule().scheduled_id().delete(0);
+    } else if !in_sync {
+        log::warn!("tick schedule drifted from the configured {}ms interval, repairing it", config.tick_interval_ms);
+        ensure_schedule(ctx, &config);
+    }
+
+    ctx.db.verify_schedule_results().identity().delete(ctx.sender);
+    ctx.db.verify_schedule_results().insert(VerifyScheduleResult { identity: ctx.sender, repaired: !in_sync });
+    Ok(())
+}
+
+#[reducer]
+fn set_paused(ctx: &ReducerContext, paused: bool) -> Result<(), String> {
+    require_admin(ctx)?;
+    pause(ctx, paused);
+    Ok(())
+}
+
+// shared with the connect and disconnect hooks, which aren't admin calls
+fn pause(ctx: &ReducerContext, paused: bool) {
+    let mut config = get_or_init_config(ctx);
+    config.paused = paused;
+    let config = ctx.db.config().id().update(config);
+    if !paused {
+        ensure_schedule(ctx, &config);
+    }
+}
+
+#[reducer]
+fn set_auto_pause(ctx: &ReducerContext, autostart_on_join: bool, autopause_on_empty: bool) -> Result<(), String> {
+    require_admin(ctx)?;
+    let mut config = get_or_init_config(ctx);
+    config.autostart_on_join = autostart_on_join;
+    config.autopause_on_empty = autopause_on_empty;
+    let config = ctx.db.config().id().update(config);
+    // a board waiting for its first player doesn't run meanwhile
+    if !config.paused && config.autostart_on_join && !ctx.db.players().iter().any(|p| p.online) {
+        pause(ctx, true);
+    }
+    Ok(())
+}
+
+#[reducer]
+fn update_heatmap_config(ctx: &ReducerContext, tile_width: u32, tile_height: u32, interval_ticks: u32) -> Result<(), String> {
+    require_admin(ctx)?;
+    if tile_width == 0 || tile_height == 0 {
+        return Err("heatmap tiles must be at least 1x1".to_string());
+    }
+    let mut config = get_or_init_config(ctx);
+    config.heatmap_tile_width = tile_width;
+    config.heatmap_tile_height = tile_height;
+    config.heatmap_interval_ticks = interval_ticks;
+    let config = ctx.db.config().id().update(config);
+
+    // old tiles have a different size, so don't wait for the next scheduled recompute
+    recompute_heatmap(ctx, &config);
+    Ok(())
+}
+
+#[reducer]
+fn update_colony_interval(ctx: &ReducerContext, interval_ticks: u32) -> Result<(), String> {
+    require_admin(ctx)?;
+    let mut config = get_or_init_config(ctx);
+    config.colony_interval_ticks = interval_ticks;
+    ctx.db.config().id().update(config);
+    Ok(())
+}
+
+fn recompute_largest_colony(ctx: &ReducerContext, config: &Config) {
+    let mut owners: HashMap<(i32, i32), u32> = ctx.db.alive_cells().iter().map(|c| ((c.x, c.y), c.player_id)).collect();
+
+    // starting in coordinate order means every colony starts at its smallest square, and with only
+    // a strictly larger colony replacing the current one, equal sizes go to the smallest start
+    let mut starts: Vec<(i32, i32)> = owners.keys().copied().collect();
+    starts.sort();
+
+    let mut largest = LargestColony { id: 0, size: 0, player_id: 0, generation: config.generation };
+    for start in starts {
+        // flood fill, removing visited cells from the snapshot as we go
+        let Some(start_owner) = owners.remove(&start) else {
+            continue;
+        };
+        let mut counts: BTreeMap<u32, u32> = BTreeMap::new();
+        let mut size = 0;
+        let mut queue = VecDeque::from([start]);
+        *counts.entry(start_owner).or_insert(0) += 1;
+        while let Some((x, y)) = queue.pop_front() {
+            size += 1;
+            for next in [(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)] {
+                if let Some(player_id) = owners.remove(&next) {
+                    *counts.entry(player_id).or_insert(0) += 1;
+                    queue.push_back(next);
+                }
+            }
+        }
+        if size > largest.size {
+            largest.size = size;
+            // ties go to the lowest player id
+            largest.player_id = counts.iter().max_by_key(|&(&player, &count)| (count, std::cmp::Reverse(player))).map(|(&player, _)| player).unwrap();
+        }
+    }
+
+    ctx.db.largest_colony().id().delete(0);
+    ctx.db.largest_colony().insert(largest);
+}
+
+fn recompute_heatmap(ctx: &ReducerContext, config: &Config) {
+    let tiles: Vec<HeatmapTile> = ctx.db.heatmap().iter().collect();
+    for tile in tiles {
+        ctx.db.heatmap().delete(tile);
+    }
+
+    let mut counts: HashMap<(i32, i32), u32> = HashMap::new();
+    for alive_cell in ctx.db.alive_cells().iter() {
+        let tile_x = alive_cell.x.div_euclid(config.heatmap_tile_width as i32);
+        let tile_y = alive_cell.y.div_euclid(config.heatmap_tile_height as i32);
+        *counts.entry((tile_x, tile_y)).or_insert(0) += 1;
+    }
+    for ((tile_x, tile_y), count) in counts {
+        ctx.db.heatmap().insert(HeatmapTile { tile_x, tile_y, count });
+    }
+}
+
+// all generations of a batch run in one transaction, so subscribers only see the final state of the batch
+#[reducer]
+fn update_generations_per_tick(ctx: &ReducerContext, generations: u32) -> Result<(), String> {
+    require_admin(ctx)?;
+    if generations == 0 || generations > MAX_GENERATIONS_PER_TICK {
+        return Err(format!("generations per tick must be between 1 and {}", MAX_GENERATIONS_PER_TICK));
+    }
+    let mut config = get_or_init_config(ctx);
+    config.generations_per_tick = generations;
+    config.generations_per_second = generations_per_second(config.generations_per_tick, config.tick_interval_ms);
+    ctx.db.config().id().update(config);
+    Ok(())
+}
+
+#[reducer]
+fn set_dynamic_ownership(ctx: &ReducerContext, enabled: bool) -> Result<(), String> {
+    require_admin(ctx)?;
+    let mut config = get_or_init_config(ctx);
+    config.dynamic_ownership = enabled;
+    ctx.db.config().id().update(config);
+    Ok(())
+}
+
+#[reducer]
+fn update_capture_threshold(ctx: &ReducerContext, threshold: u8) -> Result<(), String> {
+    require_admin(ctx)?;
+    if threshold > 8 {
+        return Err("capture threshold can't exceed 8 neighbours".to_string());
+    }
+    let mut config = get_or_init_config(ctx);
+    config.capture_threshold = threshold;
+    ctx.db.config().id().update(config);
+    Ok(())
+}
+
+#[reducer]
+fn update_spawn_protection(ctx: &ReducerContext, protection_ms: u32) -> Result<(), String> {
+    require_admin(ctx)?;
+    let mut config = get_or_init_config(ctx);
+    config.spawn_protection_ms = protection_ms;
+    ctx.db.config().id().update(config);
+    Ok(())
+}
+
+// players who joined recently enough that their cells can't be captured yet
+fn spawn_protected_players(ctx: &ReducerContext, config: &Config) -> HashSet<u32> {
+    if config.spawn_protection_ms == 0 {
+        return HashSet::new();
+    }
+    ctx.db.players().iter()
+        .filter(|p| ctx.timestamp.duration_since(p.joined_at)
+            .is_none_or(|since_join| since_join.as_millis() < config.spawn_protection_ms as u128))
+        .map(|p| p.id)
+        .collect()
+}
+
+#[reducer]
+fn set_edge_mode(ctx: &ReducerContext, mode: String) -> Result<(), String> {
+    require_admin(ctx)?;
+    EdgeMode::parse(&mode)?;
+    let mut config = get_or_init_config(ctx);
+    config.edge_mode = mode;
+    ctx.db.config().id().update(config);
+    Ok(())
+}
+
+#[reducer]
+fn add_region(ctx: &ReducerContext, bounds: Bounds, rule: String, edge_mode: String) -> Result<(), String> {
+    require_admin(ctx)?;
+    if bounds.min_x > bounds.max_x || bounds.min_y > bounds.max_y {
+        return Err("region bounds are inverted".to_string());
+    }
+    Rule::parse(&rule)?;
+    parse_region_edge(&edge_mode)?;
+    ctx.db.regions().insert(Region { id: 0, bounds, rule, edge_mode });
+    Ok(())
+}
+
+#[reducer]
+fn remove_region(ctx: &ReducerContext, id: u32) -> Result<(), String> {
+    require_admin(ctx)?;
+    ctx.db.regions().id().delete(id);
+    Ok(())
+}
+
+// the extended neighbourhood only counts squares on the canvas and ignores reflecting edges
+// and region rules, owners of born cells are still decided by the 3x3 neighbours
+struct Neighborhood {
+    radius: i32,
+    weighted: bool,
+    birth: std::ops::RangeInclusive<f32>,
+    survive: std::ops::RangeInclusive<f32>,
+}
+
+impl Neighborhood {
+    fn from_config(config: &Config) -> Option<Self> {
+        if config.neighborhood_radius <= 1 && !config.distance_weighting {
+            return None;
+        }
+        Some(Neighborhood {
+            radius: config.neighborhood_radius.clamp(1, MAX_NEIGHBORHOOD_RADIUS) as i32,
+            weighted: config.distance_weighting,
+            birth: config.birth_min..=config.birth_max,
+            survive: config.survive_min..=config.survive_max,
+        })
+    }
+
+    // in WEIGHT_SCALE units
+    fn weight(&self, distance: i32) -> u32 {
+        if self.weighted { WEIGHT_SCALE / distance as u32 } else { WEIGHT_SCALE }
+    }
+}
+
+#[reducer]
+fn update_neighborhood(
+    ctx: &ReducerContext,
+    radius: u8,
+    distance_weighting: bool,
+    birth_min: f32,
+    birth_max: f32,
+    survive_min: f32,
+    survive_max: f32,
+) -> Result<(), String> {
+    require_admin(ctx)?;
+    if radius == 0 || radius > MAX_NEIGHBORHOOD_RADIUS {
+        return Err(format!("neighborhood radius must be between 1 and {}", MAX_NEIGHBORHOOD_RADIUS));
+    }
+    for (min, max) in [(birth_min, birth_max), (survive_min, survive_max)] {
+        if !min.is_finite() || !max.is_finite() || min > max {
+            return Err(format!("invalid neighbour range {}..={}", min, max));
+        }
+    }
+    let mut config = get_or_init_config(ctx);
+    config.neighborhood_radius = radius;
+    config.distance_weighting = distance_weighting;
+    config.birth_min = birth_min;
+    config.birth_max = birth_max;
+    config.survive_min = survive_min;
+    config.survive_max = survive_max;
+    ctx.db.config().id().update(config);
+    Ok(())
+}
+
+struct RegionRules {
+    bounds: Bounds,
+    rule: Rule,
+    edge: Option<EdgeMode>,
+}
+
+fn region_rules(ctx: &ReducerContext) -> Vec<RegionRules> {
+    let mut regions: Vec<Region> = ctx.db.regions().iter().collect();
+    regions.sort_by_key(|r| r.id);
+    regions.into_iter()
+        .map(|r| RegionRules {
+            rule: Rule::parse(&r.rule).unwrap_or(Rule::CONWAY),
+            edge: parse_region_edge(&r.edge_mode).unwrap_or(None),
+            bounds: r.bounds,
+        })
+        .collect()
+}
+
+fn region_at(regions: &[RegionRules], x: i32, y: i32) -> Option<usize> {
+    regions.iter().position(|r| r.bounds.contains(x, y))
+}
+
+#[reducer]
+fn update_max_total_cells(ctx: &ReducerContext, max_total_cells: u32) -> Result<(), String> {
+    require_admin(ctx)?;
+    let mut config = get_or_init_config(ctx);
+    config.max_total_cells = max_total_cells;
+    ctx.db.config().id().update(config);
+    Ok(())
+}
+
+#[reducer]
+fn set_social_survival(ctx: &ReducerContext, enabled: bool) -> Result<(), String> {
+    require_admin(ctx)?;
+    let mut config = get_or_init_config(ctx);
+    config.social_survival = enabled;
+    ctx.db.config().id().update(config);
+    Ok(())
+}
+
+#[reducer]
+fn update_drift(ctx: &ReducerContext, drift_x: i32, drift_y: i32, interval: u32) -> Result<(), String> {
+    require_admin(ctx)?;
+    if interval == 0 {
+        return Err("drift interval must be at least 1 generation".to_string());
+    }
+    let mut config = get_or_init_config(ctx);
+    config.drift_x = drift_x;
+    config.drift_y = drift_y;
+    config.drift_interval = interval;
+    ctx.db.config().id().update(config);
+    Ok(())
+}
+
+#[reducer]
+fn set_explosive(ctx: &ReducerContext, enabled: bool) -> Result<(), String> {
+    require_admin(ctx)?;
+    let mut config = get_or_init_config(ctx);
+    config.explosive = enabled;
+    ctx.db.config().id().update(config);
+    Ok(())
+}
+
+#[reducer]
+fn set_rng_seed(ctx: &ReducerContext, seed: u64) -> Result<(), String> {
+    require_admin(ctx)?;
+    let mut config = get_or_init_config(ctx);
+    config.rng_seed = seed;
+    ctx.db.config().id().update(config);
+    Ok(())
+}
+
+// random stream for one square in the current generation, independent of the order cells are visited in
+fn cell_rng(config: &Config, x: i32, y: i32) -> Rng {
+    let coordinates = ((x as u32 as u64) << 32) | y as u32 as u64;
+    Rng::new(config.rng_seed ^ config.generation.rotate_left(17) ^ Rng::new(coordinates).next_u64())
+}
+
+#[reducer]
+fn update_mutation_percent(ctx: &ReducerContext, mutation_percent: u8) -> Result<(), String> {
+    require_admin(ctx)?;
+    if mutation_percent > 100 {
+        return Err("mutation percent can't exceed 100".to_string());
+    }
+    let mut config = get_or_init_config(ctx);
+    config.mutation_percent = mutation_percent;
+    ctx.db.config().id().update(config);
+    Ok(())
+}
+
+// draws from the seeded rng of the birth square, so replays mutate the same births
+fn mutate_owner(config: &Config, player_ids: &[u32], cell: &Cell, owner: u32) -> u32 {
+    if config.mutation_percent == 0 || player_ids.is_empty() {
+        return owner;
+    }
+    let mut rng = cell_rng(config, cell.x, cell.y);
+    if !rng.chance(config.mutation_percent) {
+        return owner;
+    }
+    player_ids[rng.next_range(0, player_ids.len() as u32) as usize]
+}
+
+#[reducer]
+fn update_max_cell_age(ctx: &ReducerContext, max_cell_age: u32) -> Result<(), String> {
+    require_admin(ctx)?;
+    let mut config = get_or_init_config(ctx);
+    config.max_cell_age = max_cell_age;
+    ctx.db.config().id().update(config);
+    Ok(())
+}
+
+#[reducer]
+fn update_seed_weight(ctx: &ReducerContext, seed_weight: u32) -> Result<(), String> {
+    require_admin(ctx)?;
+    let mut config = get_or_init_config(ctx);
+    config.seed_weight = seed_weight;
+    ctx.db.config().id().update(config);
+    Ok(())
+}
+
+#[reducer]
+fn set_color_mode(ctx: &ReducerContext, mode: String) -> Result<(), String> {
+    require_admin(ctx)?;
+    ColorMode::parse(&mode)?;
+    let mut config = get_or_init_config(ctx);
+    config.color_mode = mode;
+    ctx.db.config().id().update(config);
+    Ok(())
+}
+
+// counts holds the parents' tally and is reused for the Moore neighbourhood under dominance,
+// which unlike the parents the rule counted ignores region borders and reflecting edges,
+// seed weights count in both modes
+fn birth_owner(
+    color_mode: ColorMode,
+    owners: &HashMap<(i32, i32), u32>,
+    weighted: &HashMap<(i32, i32), (u32, u32)>,
+    cell: &Cell,
+    counts: &mut Vec<(u32, u32)>,
+) -> Option<u32> {
+    if color_mode == ColorMode::Dominance {
+        tally_neighbours(owners, cell.x, cell.y, counts);
+    }
+    add_seed_weights(weighted, cell, counts);
+    most_common_owner(counts)
+}
+
+// tallies the owners of the 8 squares around (x, y) into counts and returns how many are alive
+fn tally_neighbours(owners: &HashMap<(i32, i32), u32>, x: i32, y: i32, counts: &mut Vec<(u32, u32)>) -> u32 {
+    counts.clear();
+    let mut total = 0;
+    for nx in x - 1..=x + 1 {
+        for ny in y - 1..=y + 1 {
+            let Some(&player_id) = owners.get(&(nx, ny)).filter(|_| (nx, ny) != (x, y)) else {
+                continue;
+            };
+            total += 1;
+            match counts.iter_mut().find(|(player, _)| *player == player_id) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((player_id, 1)),
+            }
+        }
+    }
+    total
+}
+
+// ties go to the lowest player id
+fn most_common_owner(counts: &[(u32, u32)]) -> Option<u32> {
+    counts.iter().max_by_key(|&&(player, count)| (count, std::cmp::Reverse(player))).map(|&(player, _)| player)
+}
+
+#[reducer]
+fn set_auto_prune_isolated(ctx: &ReducerContext, enabled: bool) -> Result<(), String> {
+    require_admin(ctx)?;
+    let mut config = get_or_init_config(ctx);
+    config.auto_prune_isolated = enabled;
+    ctx.db.config().id().update(config);
+    Ok(())
+}
+
+fn has_alive_neighbour(ctx: &ReducerContext, x: i32, y: i32) -> bool {
+    for nx in x - 1..=x + 1 {
+        for ny in y - 1..=y + 1 {
+            if (nx != x || ny != y) && ctx.db.alive_cells().coordinates().filter((nx, ny)).next().is_some() {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+// isolated cells would die next generation anyway, removing them right away just saves the flicker
+fn prune_isolated(ctx: &ReducerContext, changes: &mut GenerationChanges) {
+    let isolated: Vec<AliveCell> = ctx.db.alive_cells().iter()
+        .filter(|c| !has_alive_neighbour(ctx, c.x, c.y))
+        .collect();
+    for alive_cell in isolated {
+        record_removal(changes, alive_cell.x, alive_cell.y);
+        ctx.db.alive_cells().delete(alive_cell);
+    }
+}
+
+#[reducer]
+fn add_admin(ctx: &ReducerContext, identity: Identity) -> Result<(), String> {
+    require_admin(ctx)?;
+    if ctx.db.admins().identity().find(identity).is_none() {
+        ctx.db.admins().insert(Admin { identity });
+    }
+    Ok(())
+}
+
+// hands every live cell to a random online player, the same seed always gives the same board
+#[reducer]
+fn shuffle_ownership(ctx: &ReducerContext, seed: u64) -> Result<(), String> {
+    require_admin(ctx)?;
+    let mut player_ids: Vec<u32> = ctx.db.players().iter().filter(|p| p.online).map(|p| p.id).collect();
+    if player_ids.is_empty() {
+        return Err("no players online".to_string());
+    }
+    player_ids.sort();
+
+    let mut alive_cells: Vec<AliveCell> = ctx.db.alive_cells().iter().collect();
+    alive_cells.sort_by_key(|c| (c.x, c.y, c.player_id));
+
+    let config = get_or_init_config(ctx);
+    let mut rng = Rng::new(seed);
+    for alive_cell in alive_cells {
+        let player_id = player_ids[rng.next_range(0, player_ids.len() as u32) as usize];
+        if player_id != alive_cell.player_id {
+            let color_hex = cell_color(ctx, &config, player_id);
+            ctx.db.alive_cells().delete(alive_cell.clone());
+            ctx.db.alive_cells().insert(AliveCell { player_id, color_hex, weight: 0, ..alive_cell });
+        }
+    }
+    Ok(())
+}
+
+// starts a fresh match: everything is validated before anything changes, then the rule, canvas
+// and speed are applied, the board, sources, scores and history are cleared and the generation
+// starts over, all in this one transaction. regions and the backdrop are part of the venue and stay
+#[reducer]
+fn new_game(ctx: &ReducerContext, ruleset: String, canvas_preset: String, tick_ms: u32) -> Result<(), String> {
+    require_admin(ctx)?;
+    Rule::parse(&ruleset)?;
+    let (width, height) = self::canvas_preset(&canvas_preset)?;
+    let loop_duration = schedule_duration(tick_ms)?;
+
+    let mut config = get_or_init_config(ctx);
+    config.rule = ruleset;
+    // the extended neighbourhood would override the new rule, so the game goes back to the 8 squares around
+    config.neighborhood_radius = 1;
+    config.distance_weighting = false;
+    config.width = width;
+    config.height = height;
+    config.tick_interval_ms = (loop_duration.to_micros() / 1000) as u32;
+    config.generations_per_second = generations_per_second(config.generations_per_tick, config.tick_interval_ms);
+    config.generation = 0;
+    config.at_capacity = false;
+    config.finished = false;
+    config.hash_chain = FNV_OFFSET_BASIS;
+    config.server_gen_timestamp_micros = ctx.timestamp.to_micros_since_unix_epoch();
+    let config = ctx.db.config().id().update(config);
+
+    let cells: Vec<AliveCell> = ctx.db.alive_cells().iter().collect();
+    for alive_cell in cells {
+        ctx.db.alive_cells().delete(alive_cell);
+    }
+    let sources: Vec<Source> = ctx.db.sources().iter().collect();
+    for source in sources {
+        ctx.db.sources().delete(source);
+    }
+    let players: Vec<Player> = ctx.db.players().iter().collect();
+    for p in players {
+        ctx.db.players().id().update(Player { score: 0, energy: config.starting_energy, ..p });
+    }
+    let stats: Vec<TickStat> = ctx.db.tick_stats().iter().collect();
+    for stat in stats {
+        ctx.db.tick_stats().delete(stat);
+    }
+    let samples: Vec<ScoreSample> = ctx.db.score_samples().iter().collect();
+    for sample in samples {
+        ctx.db.score_samples().delete(sample);
+    }
+    recompute_heatmap(ctx, &config);
+    recompute_largest_colony(ctx, &config);
+    ensure_schedule(ctx, &config);
+    Ok(())
+}
+
+// hands every live cell to one player, e.g. to turn a collaborative piece into a single-color one
+#[reducer]
+fn unify_ownership(ctx: &ReducerContext, player_id: u32) -> Result<(), String> {
+    require_admin(ctx)?;
+    if ctx.db.players().id().find(player_id).is_none() {
+        return Err(format!("no player with id {}", player_id));
+    }
+    let config = get_or_init_config(ctx);
+    let color_hex = cell_color(ctx, &config, player_id);
+
+    // neutral cells belong to player 0, which has no player row
+    let mut previous_owners: Vec<u32> = ctx.db.players().iter().map(|p| p.id).filter(|&id| id != player_id).collect();
+    previous_owners.push(0);
+    for owner in previous_owners {
+        let cells: Vec<AliveCell> = ctx.db.alive_cells().by_player().filter(owner).collect();
+        for alive_cell in cells {
+            ctx.db.alive_cells().delete(alive_cell.clone());
+            ctx.db.alive_cells().insert(AliveCell { player_id, color_hex: color_hex.clone(), weight: 0, ..alive_cell });
+        }
+    }
+    recompute_largest_colony(ctx, &config);
+    Ok(())
+}
+
+#[reducer]
+fn transform_board(ctx: &ReducerContext, op: String) -> Result<(), String> {
+    require_admin(ctx)?;
+    let transform = Transform::parse(&op)?;
+    let bounds = Bounds::canvas(&get_or_init_config(ctx));
+    move_cells(ctx, &bounds, |x, y| transform.apply(&bounds, x, y));
+    Ok(())
+}
+
+#[reducer]
+fn set_canvas_preset(ctx: &ReducerContext, name: String) -> Result<(), String> {
+    require_admin(ctx)?;
+    let (width, height) = canvas_preset(&name)?;
+    let mut config = get_or_init_config(ctx);
+    config.width = width;
+    config.height = height;
+    let config = ctx.db.config().id().update(config);
+
+    // shrinking the canvas culls cells that are now outside of it
+    let bounds = Bounds::canvas(&config);
+    let culled: Vec<AliveCell> = ctx.db.alive_cells().iter().filter(|c| !bounds.contains(c.x, c.y)).collect();
+    for alive_cell in culled {
+        ctx.db.alive_cells().delete(alive_cell);
+    }
+    Ok(())
+}
+
+// repair tool for boards that picked up several rows on one square,
+// the lowest player id keeps the square
+#[reducer]
+fn compact_board(ctx: &ReducerContext) -> Result<(), String> {
+    require_admin(ctx)?;
+    let mut by_square: BTreeMap<(i32, i32), Vec<AliveCell>> = BTreeMap::new();
+    for alive_cell in ctx.db.alive_cells().iter() {
+        by_square.entry((alive_cell.x, alive_cell.y)).or_default().push(alive_cell);
+    }
+
+    let mut removed = 0;
+    for mut rows in by_square.into_values().filter(|rows| rows.len() > 1) {
+        rows.sort_by_key(|c| (c.player_id, c.born_generation));
+        for duplicate in rows.into_iter().skip(1) {
+            ctx.db.alive_cells().delete(duplicate);
+            removed += 1;
+        }
+    }
+    log::info!("compact_board removed {removed} duplicate cells");
+    ctx.db.compact_board_results().identity().delete(ctx.sender);
+    ctx.db.compact_board_results().insert(CompactBoardResult { identity: ctx.sender, removed });
+    Ok(())
+}
+
+// moves every cell to a new position, dropping the ones that end up outside of the canvas
+fn move_cells(ctx: &ReducerContext, bounds: &Bounds, to: impl Fn(i32, i32) -> (i32, i32)) {
+    // cells landing on the same square keep the lowest player id, so the result doesn't depend on iteration order
+    let mut moved: BTreeMap<(i32, i32), AliveCell> = BTreeMap::new();
+    let alive_cells: Vec<AliveCell> = ctx.db.alive_cells().iter().collect();
+    for alive_cell in alive_cells {
+        let (x, y) = to(alive_cell.x, alive_cell.y);
+        ctx.db.alive_cells().delete(alive_cell.clone());
+        let moved_cell = AliveCell { x, y, ..alive_cell };
+        if !bounds.contains(x, y) {
+            continue;
+        }
+        match moved.get(&(x, y)) {
+            Some(existing) if existing.player_id <= moved_cell.player_id => {}
+            _ => {
+                moved.insert((x, y), moved_cell);
+            }
+        }
+    }
+
+    for moved_cell in moved.into_values() {
+        ctx.db.alive_cells().insert(moved_cell);
+    }
+}
+
+#[reducer]
+fn tick(ctx: &ReducerContext, _arg: TickSchedule) -> Result<(), String> {
+    // clients advance the board through step, which is for admins only
+    if ctx.sender != ctx.identity() {
+        return Err("tick can only be run by the scheduler".to_string());
+    }
+    let mut config = get_or_init_config(ctx);
+    if config.paused {
+        return Ok(());
+    }
+    // something rescheduled a finished board
+    if config.finished && reached_max_generation(&config) {
+        ctx.db.tick_schedule().scheduled_id().delete(0);
+        return Ok(());
+    }
+    let previous_generation = config.generation;
+    let generations = config.generations_per_tick.clamp(1, MAX_GENERATIONS_PER_TICK);
+    let mut at_capacity = false;
+    let (mut births, mut deaths) = (0, 0);
+    for _ in 0..generations {
+        if reached_max_generation(&config) {
+            break;
+        }
+        let changes = run_generation(ctx, &mut config);
+        at_capacity |= changes.at_capacity;
+        births += changes.births.len() as u32;
+        deaths += changes.deaths.len() as u32;
+    }
+    config.at_capacity = at_capacity;
+    config.server_gen_timestamp_micros = ctx.timestamp.to_micros_since_unix_epoch();
+    if reached_max_generation(&config) {
+        config.finished = true;
+        ctx.db.tick_schedule().scheduled_id().delete(0);
+    }
+    let config = ctx.db.config().id().update(config);
+    log_tick_stats(ctx, config.generation, births, deaths);
+
+    // heatmap is a coarse overview, recomputing it every few generations is plenty
+    let interval = config.heatmap_interval_ticks as u64;
+    if interval > 0 && previous_generation / interval != config.generation / interval {
+        recompute_heatmap(ctx, &config);
+    }
+
+    // flood fill over the whole board, too costly to do every generation
+    let interval = config.colony_interval_ticks as u64;
+    if interval > 0 && previous_generation / interval != config.generation / interval {
+        recompute_largest_colony(ctx, &config);
+    }
+
+    let interval = config.score_history_interval as u64;
+    if interval > 0 && previous_generation / interval != config.generation / interval {
+        sample_scores(ctx, &config);
+    }
+    Ok(())
+}
+
+fn reached_max_generation(config: &Config) -> bool {
+    config.max_generation > 0 && config.generation >= config.max_generation
+}
+
+// moving the cap past the current generation, or to 0, lets a finished board run again
+#[reducer]
+fn update_max_generation(ctx: &ReducerContext, max_generation: u64) -> Result<(), String> {
+    require_admin(ctx)?;
+    let mut config = get_or_init_config(ctx);
+    config.max_generation = max_generation;
+    if config.finished && !reached_max_generation(&config) {
+        config.finished = false;
+        ensure_schedule(ctx, &config);
+    }
+    ctx.db.config().id().update(config);
+    Ok(())
+}
+
+// advances the board by one generation on demand and reports exactly which cells changed
+#[reducer]
+fn step(ctx: &ReducerContext) -> Result<(), String> {
+    require_admin(ctx)?;
+    let mut config = get_or_init_config(ctx);
+    let changes = run_generation(ctx, &mut config);
+    config.at_capacity = changes.at_capacity;
+    config.server_gen_timestamp_micros = ctx.timestamp.to_micros_since_unix_epoch();
+    let config = ctx.db.config().id().update(config);
+    log_tick_stats(ctx, config.generation, changes.births.len() as u32, changes.deaths.len() as u32);
+
+    ctx.db.step_results().identity().delete(ctx.sender);
+    ctx.db.step_results().insert(StepResult {
+        identity: ctx.sender,
+        generation: config.generation,
+        births: changes.births,
+        deaths: changes.deaths,
+    });
+    Ok(())
+}
+
+// debugging aid for rule authors: live neighbours of every square in a rectangle, recomputed on
+// demand. counts are the plain 8 squares around, region borders and reflecting edges aren't applied
+#[reducer]
+fn neighbor_counts_in_rect(ctx: &ReducerContext, x0: i32, y0: i32, x1: i32, y1: i32) -> Result<(), String> {
+    require_admin(ctx)?;
+    let (min_x, max_x) = (x0.min(x1), x0.max(x1));
+    let (min_y, max_y) = (y0.min(y1), y0.max(y1));
+    let area = (max_x as i64 - min_x as i64 + 1) * (max_y as i64 - min_y as i64 + 1);
+    if area > MAX_NEIGHBOR_DUMP_AREA {
+        return Err(format!("rectangle covers {} squares, at most {} allowed", area, MAX_NEIGHBOR_DUMP_AREA));
+    }
+
+    let alive: HashSet<(i32, i32)> = ctx.db.alive_cells().coordinates()
+        .filter(min_x.saturating_sub(1)..=max_x.saturating_add(1))
+        .filter(|c| (min_y.saturating_sub(1)..=max_y.saturating_add(1)).contains(&c.y))
+        .map(|c| (c.x, c.y))
+        .collect();
+    let mut counts = Vec::with_capacity(area as usize);
+    for y in min_y..=max_y {
+        for x in min_x..=max_x {
+            let mut count = 0;
+            for nx in x.saturating_sub(1)..=x.saturating_add(1) {
+                for ny in y.saturating_sub(1)..=y.saturating_add(1) {
+                    if (nx, ny) != (x, y) && alive.contains(&(nx, ny)) {
+                        count += 1;
+                    }
+                }
+            }
+            counts.push(NeighborCount { x, y, count });
+        }
+    }
+
+    ctx.db.neighbor_count_results().identity().delete(ctx.sender);
+    ctx.db.neighbor_count_results().insert(NeighborCountResult { identity: ctx.sender, counts });
+    Ok(())
+}
+
+// previews the board a few generations ahead without changing it. the generations really run
+// so every rule applies exactly as in tick, then the board, scores and energy are put back
+#[reducer]
+fn simulate(ctx: &ReducerContext, generations: u32) -> Result<(), String> {
+    if generations > MAX_SIMULATED_GENERATIONS {
+        return Err(format!("can simulate at most {} generations", MAX_SIMULATED_GENERATIONS));
+    }
+    let mut config = get_or_init_config(ctx);
+    let board: Vec<AliveCell> = ctx.db.alive_cells().iter().collect();
+    let scores: Vec<(u32, u64, u32)> = ctx.db.players().iter().map(|p| (p.id, p.score, p.energy)).collect();
+
+    for _ in 0..generations {
+        run_generation(ctx, &mut config);
+    }
+    let cells: Vec<AliveCell> = ctx.db.alive_cells().iter().collect();
+
+    for alive_cell in cells.iter().cloned() {
+        ctx.db.alive_cells().delete(alive_cell);
+    }
+    for alive_cell in board {
+        ctx.db.alive_cells().insert(alive_cell);
+    }
+    for (id, score, energy) in scores {
+        if let Some(p) = ctx.db.players().id().find(id) {
+            ctx.db.players().id().update(Player { score, energy, ..p });
+        }
+    }
+
+    ctx.db.simulate_results().identity().delete(ctx.sender);
+    ctx.db.simulate_results().insert(SimulateResult { identity: ctx.sender, generation: config.generation, cells });
+    Ok(())
+}
+
+#[reducer]
+fn rules(ctx: &ReducerContext) {
+    let rules = rule_summary(&get_or_init_config(ctx));
+    ctx.db.rules_results().identity().delete(ctx.sender);
+    ctx.db.rules_results().insert(RulesResult { identity: ctx.sender, rules });
+}
+
+fn rule_summary(config: &Config) -> RuleSummary {
+    RuleSummary {
+        width: config.width,
+        height: config.height,
+        margin: config.margin,
+        edge_mode: config.edge_mode.clone(),
+        tick_interval_ms: config.tick_interval_ms,
+        generations_per_tick: config.generations_per_tick,
+        generations_per_second: config.generations_per_second,
+        max_generation: config.max_generation,
+        rule: config.rule.clone(),
+        neighborhood_radius: config.neighborhood_radius,
+        distance_weighting: config.distance_weighting,
+        birth_min: config.birth_min,
+        birth_max: config.birth_max,
+        survive_min: config.survive_min,
+        survive_max: config.survive_max,
+        max_cell_age: config.max_cell_age,
+        social_survival: config.social_survival,
+        explosive: config.explosive,
+        smooth: config.smooth,
+        auto_prune_isolated: config.auto_prune_isolated,
+        enforce_symmetry: config.enforce_symmetry.clone(),
+        drift_x: config.drift_x,
+        drift_y: config.drift_y,
+        drift_interval: config.drift_interval,
+        rng_seed: config.rng_seed,
+        color_mode: config.color_mode.clone(),
+        owner_color_mode: config.owner_color_mode.clone(),
+        dynamic_ownership: config.dynamic_ownership,
+        capture_threshold: config.capture_threshold,
+        spawn_protection_ms: config.spawn_protection_ms,
+        seed_weight: config.seed_weight,
+        mutation_percent: config.mutation_percent,
+        contagion_percent: config.contagion_percent,
+        max_total_cells: config.max_total_cells,
+        placement_cost: config.placement_cost,
+        starting_energy: config.starting_energy,
+        toggle_removes_any: config.toggle_removes_any,
+        mix_on_overlap: config.mix_on_overlap,
+        overlap_owner: config.overlap_owner.clone(),
+    }
+}
+
+// last n rows of tick_stats for sparklines, n is capped to what's retained
+#[reducer]
+fn recent_stats(ctx: &ReducerContext, n: u32) {
+    let n = (n as u64).min(TICK_STATS_RETENTION) as usize;
+    let mut stats: Vec<TickStat> = ctx.db.tick_stats().iter().collect();
+    stats.sort_by_key(|stat| stat.generation);
+    let stats = stats.split_off(stats.len().saturating_sub(n));
+
+    ctx.db.recent_stats_results().identity().delete(ctx.sender);
+    ctx.db.recent_stats_results().insert(RecentStatsResult { identity: ctx.sender, stats });
+}
+
+fn sample_scores(ctx: &ReducerContext, config: &Config) {
+    ctx.db.score_samples().generation().delete(..config.generation.saturating_sub(config.score_history_retention as u64));
+    let samples: Vec<ScoreSample> = ctx.db.players().iter()
+        .map(|p| ScoreSample { player_id: p.id, generation: config.generation, score: p.score })
+        .collect();
+    for sample in samples {
+        ctx.db.score_samples().insert(sample);
+    }
+}
+
+#[reducer]
+fn update_score_history(ctx: &ReducerContext, interval: u32, retention: u32) -> Result<(), String> {
+    require_admin(ctx)?;
+    let mut config = get_or_init_config(ctx);
+    config.score_history_interval = interval;
+    config.score_history_retention = retention;
+    ctx.db.config().id().update(config);
+    Ok(())
+}
+
+// a player's score over time, for a progress graph
+#[reducer]
+fn score_history(ctx: &ReducerContext, player_id: u32) {
+    let mut points: Vec<ScorePoint> = ctx.db.score_samples().player_id().filter(player_id)
+        .map(|s| ScorePoint { generation: s.generation, score: s.score })
+        .collect();
+    points.sort_by_key(|p| p.generation);
+
+    ctx.db.score_history_results().identity().delete(ctx.sender);
+    ctx.db.score_history_results().insert(ScoreHistoryResult { identity: ctx.sender, player_id, points });
+}
+
+fn log_tick_stats(ctx: &ReducerContext, generation: u64, births: u32, deaths: u32) {
+    ctx.db.tick_stats().generation().delete(..generation.saturating_sub(TICK_STATS_RETENTION));
+    ctx.db.tick_stats().insert(TickStat {
+        generation,
+        births,
+        deaths,
+        population: ctx.db.alive_cells().count() as u32,
+    });
+}
+
+// one generation including the phases that run on top of the Conway rules, the caller persists the config
+fn run_generation(ctx: &ReducerContext, config: &mut Config) -> GenerationChanges {
+    let mut changes = advance(ctx, config);
+    config.generation += 1;
+    smooth(ctx, config, &mut changes);
+    enforce_symmetry(ctx, config, &mut changes);
+    spread_contagion(ctx, config);
+
+    // drift is applied on top of the finished generation, it's not part of the Conway rules
+    let drifts = config.drift_x != 0 || config.drift_y != 0;
+    if drifts && config.generation.is_multiple_of(config.drift_interval.max(1) as u64) {
+        let (dx, dy) = (config.drift_x, config.drift_y);
+        move_cells(ctx, &Bounds::canvas(config), |x, y| (x.saturating_add(dx), y.saturating_add(dy)));
+    }
+
+    let emitted = emit_sources(ctx, config);
+    changes.births.extend(emitted);
+
+    // part of the generation so the hash chain is the same whether it ran from tick, step or simulate
+    if config.auto_prune_isolated {
+        prune_isolated(ctx, &mut changes);
+    }
+
+    config.hash_chain = fnv1a(config.hash_chain, &board_hash(ctx).to_le_bytes());
+    changes
+}
+
+// 64-bit FNV-1a continued from `hash`, stable across platforms and compiler versions unlike std's hasher
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |h, &b| (h ^ b as u64).wrapping_mul(0x0000_0100_0000_01B3))
+}
+
+// hash of who lives where, independent of the order rows come out of the table
+fn board_hash(ctx: &ReducerContext) -> u64 {
+    let mut cells: Vec<(i32, i32, u32)> = ctx.db.alive_cells().iter().map(|c| (c.x, c.y, c.player_id)).collect();
+    cells.sort();
+    cells.iter().fold(FNV_OFFSET_BASIS, |hash, &(x, y, player_id)| {
+        let hash = fnv1a(hash, &x.to_le_bytes());
+        let hash = fnv1a(hash, &y.to_le_bytes());
+        fnv1a(hash, &player_id.to_le_bytes())
+    })
+}
+
+// refills empty source squares with the source owner's cell, an occupied square is left alone
+fn emit_sources(ctx: &ReducerContext, config: &Config) -> Vec<Cell> {
+    let mut sources: Vec<Source> = ctx.db.sources().iter().collect();
+    sources.sort_by_key(|s| s.id);
+
+    let mut emitted = Vec::new();
+    for source in sources {
+        let (x, y) = (source.x, source.y);
+        if ctx.db.alive_cells().coordinates().filter((x, y)).next().is_some() {
+            continue;
+        }
+        if !has_room_for(ctx, config, 1) {
+            break;
+        }
+        // a source whose owner is out of energy stays dry until they earn some
+        if spend_energy(ctx, config, source.player_id, 1).is_err() {
+            continue;
+        }
+        let color_hex = cell_color(ctx, config, source.player_id);
+        ctx.db.alive_cells().insert(AliveCell { x, y, player_id: source.player_id, born_generation: config.generation, color_hex, weight: 0 });
+        emitted.push(Cell { x, y });
+    }
+    emitted
+}
+
+// a cell only lives if every image of it under the symmetry is alive too, so asymmetric births
+// are undone and the symmetric part of the board carries on. rotate90 on a non-square canvas
+// maps some cells off the canvas, those never have a full set of images and die
+fn enforce_symmetry(ctx: &ReducerContext, config: &Config, changes: &mut GenerationChanges) {
+    if config.enforce_symmetry == "none" {
+        return;
+    }
+    let Ok(transform) = Transform::parse(&config.enforce_symmetry) else {
+        return;
+    };
+    let bounds = Bounds::canvas(config);
+    let alive: HashSet<(i32, i32)> = ctx.db.alive_cells().iter().map(|c| (c.x, c.y)).collect();
+    let has_all_images = |x: i32, y: i32| {
+        let mut image = transform.apply(&bounds, x, y);
+        for _ in 0..4 {
+            if !alive.contains(&image) {
+                return false;
+            }
+            if image == (x, y) {
+                return true;
+            }
+            image = transform.apply(&bounds, image.0, image.1);
+        }
+        false
+    };
+    let mut broken: Vec<(i32, i32)> = alive.iter().copied().filter(|&(x, y)| !has_all_images(x, y)).collect();
+    broken.sort();
+
+    for (x, y) in broken {
+        ctx.db.alive_cells().coordinates().delete((x, y));
+        record_removal(changes, x, y);
+    }
+}
+
+// a cell removed by a later phase either undoes its birth this generation or is a death
+fn record_removal(changes: &mut GenerationChanges, x: i32, y: i32) {
+    match changes.births.iter().position(|c| c.x == x && c.y == y) {
+        Some(i) => {
+            changes.births.remove(i);
+        }
+        None => changes.deaths.push(Cell { x, y }),
+    }
+}
+
+// majority vote over the 8 neighbours of the finished generation, all squares decided at once:
+// an empty square with 5 or more live neighbours fills in for the most common neighbouring owner,
+// a live cell with at most 1 live neighbour is removed
+fn smooth(ctx: &ReducerContext, config: &Config, changes: &mut GenerationChanges) {
+    if !config.smooth {
+        return;
+    }
+    let bounds = Bounds::canvas(config);
+    let owners: HashMap<(i32, i32), u32> = ctx.db.alive_cells().iter().map(|c| ((c.x, c.y), c.player_id)).collect();
+    let SmoothingChanges { lonely, fills } = smoothing_changes(&owners, &bounds);
+
+    for (x, y) in lonely {
+        ctx.db.alive_cells().coordinates().delete((x, y));
+        record_removal(changes, x, y);
+    }
+    // in coordinate order so the cap skips the same fills on every replay
+    for ((x, y), player_id) in fills {
+        if !has_room_for(ctx, config, 1) {
+            changes.at_capacity = true;
+            break;
+        }
+        let color_hex = cell_color(ctx, config, player_id);
+        ctx.db.alive_cells().insert(AliveCell { x, y, player_id, born_generation: config.generation, color_hex, weight: 0 });
+        changes.births.push(Cell { x, y });
+    }
+}
+
+struct SmoothingChanges {
+    // live cells with at most 1 live neighbour, sorted
+    lonely: Vec<(i32, i32)>,
+    // empty squares with 5 or more live neighbours and the owner they fill in for
+    fills: BTreeMap<(i32, i32), u32>,
+}
+
+// both lists are decided from the same owners snapshot, removing a lonely cell doesn't change a fill
+fn smoothing_changes(owners: &HashMap<(i32, i32), u32>, bounds: &Bounds) -> SmoothingChanges {
+    let mut lonely: Vec<(i32, i32)> = Vec::new();
+    let mut fills: BTreeMap<(i32, i32), u32> = BTreeMap::new();
+    // one tally buffer for every probed square, at most 8 owners fit without regrowing
+    let mut counts: Vec<(u32, u32)> = Vec::with_capacity(8);
+    for &(x, y) in owners.keys() {
+        if tally_neighbours(owners, x, y, &mut counts) <= 1 {
+            lonely.push((x, y));
+        }
+        for nx in x - 1..=x + 1 {
+            for ny in y - 1..=y + 1 {
+                if owners.contains_key(&(nx, ny)) || fills.contains_key(&(nx, ny)) || !bounds.contains(nx, ny) {
+                    continue;
+                }
+                if tally_neighbours(owners, nx, ny, &mut counts) >= 5 {
+                    fills.insert((nx, ny), most_common_owner(&counts).unwrap_or(0));
+                }
+            }
+        }
+    }
+    lonely.sort();
+    SmoothingChanges { lonely, fills }
+}
+
+// recolors without moving anything: every live cell may adopt the owner of one of its live
+// neighbours, all picks are made from the board as the generation left it
+fn spread_contagion(ctx: &ReducerContext, config: &Config) {
+    if config.contagion_percent == 0 {
+        return;
+    }
+    let owners: BTreeMap<(i32, i32), u32> = ctx.db.alive_cells().iter().map(|c| ((c.x, c.y), c.player_id)).collect();
+    let adoptions = contagion_adoptions(config, &owners, &spawn_protected_players(ctx, config));
+    for ((x, y), player_id) in adoptions {
+        if let Some(alive_cell) = ctx.db.alive_cells().coordinates().filter((x, y)).next() {
+            let color_hex = cell_color(ctx, config, player_id);
+            ctx.db.alive_cells().delete(alive_cell.clone());
+            ctx.db.alive_cells().insert(AliveCell { player_id, color_hex, weight: 0, ..alive_cell });
+        }
+    }
+}
+
+// squares whose cell changes owner, cells of spawn protected players keep theirs
+fn contagion_adoptions(config: &Config, owners: &BTreeMap<(i32, i32), u32>, protected_players: &HashSet<u32>) -> Vec<((i32, i32), u32)> {
+    let mut adoptions: Vec<((i32, i32), u32)> = Vec::new();
+    for (&(x, y), &owner) in owners {
+        if protected_players.contains(&owner) {
+            continue;
+        }
+        // salted so it doesn't repeat the draws scatter makes for the same square next generation
+        let mut rng = Rng::new(cell_rng(config, x, y).next_u64() ^ 0xC0A7_A610);
+        if !rng.chance(config.contagion_percent) {
+            continue;
+        }
+        let neighbours: Vec<u32> = (x - 1..=x + 1)
+            .flat_map(|nx| (y - 1..=y + 1).map(move |ny| (nx, ny)))
+            .filter(|&square| square != (x, y))
+            .filter_map(|square| owners.get(&square).copied())
+            .collect();
+        if neighbours.is_empty() {
+            continue;
+        }
+        let adopted = neighbours[rng.next_range(0, neighbours.len() as u32) as usize];
+        if adopted != owner {
+            adoptions.push(((x, y), adopted));
+        }
+    }
+    adoptions
+}
+
+#[reducer]
+fn update_contagion_percent(ctx: &ReducerContext, contagion_percent: u8) -> Result<(), String> {
+    require_admin(ctx)?;
+    if contagion_percent > 100 {
+        return Err("contagion percent can't exceed 100".to_string());
+    }
+    let mut config = get_or_init_config(ctx);
+    config.contagion_percent = contagion_percent;
+    ctx.db.config().id().update(config);
+    Ok(())
+}
+
+#[reducer]
+fn set_smooth(ctx: &ReducerContext, enabled: bool) -> Result<(), String> {
+    require_admin(ctx)?;
+    let mut config = get_or_init_config(ctx);
+    config.smooth = enabled;
+    ctx.db.config().id().update(config);
+    Ok(())
+}
+
+#[reducer]
+fn set_enforce_symmetry(ctx: &ReducerContext, symmetry: String) -> Result<(), String> {
+    require_admin(ctx)?;
+    if symmetry != "none" {
+        Transform::parse(&symmetry)?;
+    }
+    let mut config = get_or_init_config(ctx);
+    config.enforce_symmetry = symmetry;
+    ctx.db.config().id().update(config);
+    Ok(())
+}
+
+// picks a few random squares around an exploding cell that were empty at the start of the generation
+fn scatter(config: &Config, owners: &HashMap<(i32, i32), u32>, exploding: &AliveCell) -> Vec<AliveCell> {
+    let cell = Cell { x: exploding.x, y: exploding.y };
+    let bounds = Bounds::canvas(config);
+    let mut empty: Vec<(i32, i32)> = Vec::new();
+    for x in cell.x - 1..=cell.x + 1 {
+        for y in cell.y - 1..=cell.y + 1 {
+            if bounds.contains(x, y) && !owners.contains_key(&(x, y)) {
+                empty.push((x, y));
+            }
+        }
+    }
+
+    let mut rng = cell_rng(config, cell.x, cell.y);
+    let mut scattered = Vec::new();
+    while !empty.is_empty() && scattered.len() < EXPLOSION_SCATTER_CELLS {
+        let (x, y) = empty.swap_remove(rng.next_range(0, empty.len() as u32) as usize);
+        scattered.push(AliveCell { x, y, born_generation: config.generation + 1, weight: 0, ..exploding.clone() });
+    }
+    scattered
+}
+
+// tallies neighbour owners into the caller's buffer, clearing it first
+fn count_owners(neighbors: &[u32], counts: &mut Vec<(u32, u32)>) {
+    counts.clear();
+    for &neighbor in neighbors {
+        match counts.iter_mut().find(|(player, _)| *player == neighbor) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((neighbor, 1)),
+        }
+    }
+}
+
+// weighted parents count extra towards their owner in the birth tally
+fn add_seed_weights(weighted: &HashMap<(i32, i32), (u32, u32)>, cell: &Cell, counts: &mut [(u32, u32)]) {
+    if weighted.is_empty() {
+        return;
+    }
+    for x in cell.x - 1..=cell.x + 1 {
+        for y in cell.y - 1..=cell.y + 1 {
+            let Some(&(player_id, weight)) = weighted.get(&(x, y)) else {
+                continue;
+            };
+            if let Some((_, count)) = counts.iter_mut().find(|(player, _)| *player == player_id) {
+                *count += weight;
+            }
+        }
+    }
+}
+
+// surviving cell is captured by a neighbouring player who outnumbers its owner
+// and holds at least threshold of its neighbours
+fn invader(counts: &[(u32, u32)], owner: u32, threshold: u32) -> Option<u32> {
+    let own_count = owner_count(counts, owner);
+    counts.iter()
+        .filter(|&&(player, count)| player != owner && count > own_count && count >= threshold)
+        .max_by_key(|&&(player, count)| (count, std::cmp::Reverse(player)))
+        .map(|&(player, _)| player)
+}
+
+// old age kills regardless of neighbours, age is counted in the generation being produced
+fn too_old(max_cell_age: u32, generation: u64, born_generation: u64) -> bool {
+    max_cell_age > 0 && (generation + 1).saturating_sub(born_generation) > max_cell_age as u64
+}
+
+fn owner_count(counts: &[(u32, u32)], player_id: u32) -> u32 {
+    counts.iter().find(|&&(player, _)| player == player_id).map_or(0, |&(_, count)| count)
+}
+
+// lone cell among strangers dies even with a healthy neighbour count
+fn survives_socially(counts: &[(u32, u32)], owner: u32) -> bool {
+    owner_count(counts, owner) > 0
+}
+
+struct NeighbourCounts {
+    // neighbour owners of every square a live cell touches
+    by_cell: HashMap<Cell, Vec<u32>>,
+    // extended neighbourhood sums in WEIGHT_SCALE units, empty without one
+    weighted_sums: HashMap<(i32, i32), u32>,
+}
+
+fn count_neighbours(
+    alive: &[(i32, i32, u32)],
+    bounds: &Bounds,
+    edge_mode: EdgeMode,
+    regions: &[RegionRules],
+    neighborhood: Option<&Neighborhood>,
+) -> NeighbourCounts {
+    // most live cells share candidates with their neighbours, a few per cell avoids regrowing the map mid scan
+    let mut neighbours_by_cell: HashMap<Cell, Vec<u32>> = HashMap::with_capacity(alive.len() * 3);
+    let mut weighted_sums: HashMap<(i32, i32), u32> = HashMap::new();
+    for &(cell_x, cell_y, player_id) in alive {
+        let reflects = edge_mode == EdgeMode::Reflect && bounds.contains(cell_x, cell_y);
+        let home = region_at(regions, cell_x, cell_y);
+        for x in cell_x - 1..=cell_x + 1 {
+            for y in cell_y - 1..=cell_y + 1 {
+                // make sure to put empty vec as neighbours even for "self" cell before continuing to avoid cell being ignore in later logic
+                let neighbors = neighbours_by_cell.entry(Cell{x,y}).or_default();
+                if x == cell_x && y == cell_y {
+                    continue;
+                }
+                if reflects && !bounds.contains(x, y) {
+                    // mirroring the neighbour back onto the canvas is the same as the canvas cell seeing the mirror image
+                    let mirrored = Cell { x: reflect(x, bounds.min_x, bounds.max_x), y: reflect(y, bounds.min_y, bounds.max_y) };
+                    neighbours_by_cell.entry(mirrored).or_default().push(player_id);
+                    continue;
+                }
+                let target = region_at(regions, x, y);
+                if target != home {
+                    // crossing a region border, closed borders stop the neighbour from being seen
+                    match home.map(|i| &regions[i]) {
+                        Some(RegionRules { edge: Some(EdgeMode::Reflect), bounds: region, .. }) => {
+                            let mirrored = Cell { x: reflect(x, region.min_x, region.max_x), y: reflect(y, region.min_y, region.max_y) };
+                            neighbours_by_cell.entry(mirrored).or_default().push(player_id);
+                            continue;
+                        }
+                        Some(RegionRules { edge: Some(EdgeMode::Kill), .. }) => continue,
+                        _ => {}
+                    }
+                    if target.is_some_and(|i| regions[i].edge.is_some()) {
+                        continue;
+                    }
+                }
+                neighbors.push(player_id);
+            }
+        }
+
+        if let Some(neighborhood) = neighborhood {
+            let r = neighborhood.radius;
+            for x in cell_x - r..=cell_x + r {
+                for y in cell_y - r..=cell_y + r {
+                    let distance = (x - cell_x).abs().max((y - cell_y).abs());
+                    if distance == 0 || !bounds.contains(x, y) {
+                        continue;
+                    }
+                    neighbours_by_cell.entry(Cell { x, y }).or_default();
+                    *weighted_sums.entry((x, y)).or_insert(0) += neighborhood.weight(distance);
+                }
+            }
+        }
+    }
+
+    NeighbourCounts { by_cell: neighbours_by_cell, weighted_sums }
+}
+
+fn advance(ctx: &ReducerContext, config: &Config) -> GenerationChanges {
+    let protected_players = spawn_protected_players(ctx, config);
+    let edge_mode = EdgeMode::parse(&config.edge_mode).unwrap_or(EdgeMode::Kill);
+    let bounds = Bounds::canvas(config);
+    let color_mode = ColorMode::parse(&config.color_mode).unwrap_or(ColorMode::Majority);
+    let regions = region_rules(ctx);
+    let neighborhood = Neighborhood::from_config(config);
+    let board_rule = Rule::parse(&config.rule).unwrap_or(Rule::CONWAY);
+    let mut player_ids: Vec<u32> = if config.mutation_percent > 0 { ctx.db.players().iter().map(|p| p.id).collect() } else { Vec::new() };
+    player_ids.sort();
+    // only the snapshot and synced modes store colors on cells
+    let colors: HashMap<u32, String> = match OwnerColorMode::parse(&config.owner_color_mode) {
+        Ok(mode) if mode.stores_color() => ctx.db.players().iter().map(|p| (p.id, p.color_hex)).collect(),
+        _ => HashMap::new(),
+    };
+    let mut population: HashMap<u32, u64> = HashMap::new();
+    // owners at the start of the generation
+    let mut owners: HashMap<(i32, i32), u32> = HashMap::new();
+    // (owner, weight) of the cells that still carry seed weight
+    let mut weighted: HashMap<(i32, i32), (u32, u32)> = HashMap::new();
+    let mut alive: Vec<(i32, i32, u32)> = Vec::new();
+    for alive_cell in ctx.db.alive_cells().iter() {
+        *population.entry(alive_cell.player_id).or_insert(0) += 1;
+        owners.insert((alive_cell.x, alive_cell.y), alive_cell.player_id);
+        if alive_cell.weight > 0 {
+            weighted.insert((alive_cell.x, alive_cell.y), (alive_cell.player_id, alive_cell.weight));
+        }
+        alive.push((alive_cell.x, alive_cell.y, alive_cell.player_id));
+    }
+    let NeighbourCounts { by_cell: neighbours_by_cell, weighted_sums } = count_neighbours(&alive, &bounds, edge_mode, &regions, neighborhood.as_ref());
+
+    for (player_id, live_cells) in population {
+        if let Some(p) = ctx.db.players().id().find(player_id) {
+            let energy = p.energy.saturating_add(live_cells.min(u32::MAX as u64) as u32);
+            ctx.db.players().id().update(Player { score: p.score + live_cells, energy, ..p });
+        }
+    }
+
+    let mut births: Vec<AliveCell> = Vec::new();
+    let mut deaths: Vec<Cell> = Vec::new();
+    // (player, neighbour count) pairs, a cell has at most a handful of distinct neighbouring owners
+    // so a reused vec beats allocating a map per cell
+    let mut counts: Vec<(u32, u32)> = Vec::with_capacity(8);
     for cell in neighbours_by_cell.keys() {
 
-        let is_alive = ctx.db.alive_cells().coordinates().filter((cell.x, cell.y)).next().is_some();
+        let alive_cell = ctx.db.alive_cells().coordinates().filter((cell.x, cell.y)).next();
+        let is_alive = alive_cell.is_some();
 
         // kill cells outside of the canvas
-        if is_alive && (cell.x < MIN_X || cell.x > MAX_X || cell.y < MIN_Y || cell.y > MAX_Y) {
+        if is_alive && !bounds.contains(cell.x, cell.y) {
             ctx.db.alive_cells().coordinates().delete((cell.x, cell.y));
+            deaths.push(*cell);
+            continue;
         }
 
-        let neighbors = neighbours_by_cell.get(cell).unwrap();
-        let mut counts: HashMap<u32, u32> = HashMap::new();
-        for neighbor in neighbors {
-            let count = counts.entry(*neighbor).or_insert(0);
-            *count += 1;
+        if alive_cell.as_ref().is_some_and(|c| too_old(config.max_cell_age, config.generation, c.born_generation)) {
+            ctx.db.alive_cells().coordinates().delete((cell.x, cell.y));
+            deaths.push(*cell);
+            continue;
         }
+
+        let neighbors = neighbours_by_cell.get(cell).unwrap();
+        count_owners(neighbors, &mut counts);
         let total_count = neighbors.len();
+        let rule = region_at(&regions, cell.x, cell.y).map_or(board_rule, |i| regions[i].rule);
+        let (births_here, survives_here) = match &neighborhood {
+            Some(neighborhood) => {
+                let sum = weighted_sums.get(&(cell.x, cell.y)).copied().unwrap_or(0) as f32 / WEIGHT_SCALE as f32;
+                (neighborhood.birth.contains(&sum), neighborhood.survive.contains(&sum))
+            }
+            None => (rule.births(total_count), rule.survives(total_count)),
+        };
 
-        if total_count == 3 {
-            // count = 3 -> cell becomes (or stays) alive
-            if !is_alive {
-                let most_common_player_id = counts.iter().max_by_key(|&(_, count)| count).map(|(&player, _)| player).unwrap_or(0);
-                ctx.db.alive_cells().insert(AliveCell { x: cell.x, y: cell.y, player_id: most_common_player_id });
+        if !is_alive {
+            if births_here {
+                // the cell is dead, so its counts aren't needed for anything after the birth
+                let most_common_player_id = birth_owner(color_mode, &owners, &weighted, cell, &mut counts).unwrap_or(0);
+                let owner = mutate_owner(config, &player_ids, cell, most_common_player_id);
+                births.push(AliveCell {
+                    x: cell.x,
+                    y: cell.y,
+                    player_id: owner,
+                    born_generation: config.generation + 1,
+                    color_hex: colors.get(&owner).cloned().unwrap_or_default(),
+                    weight: 0,
+                });
             }
-        } else if total_count != 2 && is_alive {
-            // count != 2 (or 3) -> cell dies
+        } else if !survives_here {
             ctx.db.alive_cells().coordinates().delete((cell.x, cell.y));
+            deaths.push(*cell);
+            if let Some(exploding) = alive_cell.as_ref().filter(|_| config.explosive && total_count >= 7) {
+                births.extend(scatter(config, &owners, exploding));
+            }
+            continue;
+        }
+
+        if let Some(alive_cell) = alive_cell.as_ref().filter(|_| config.social_survival) {
+            if !survives_socially(&counts, alive_cell.player_id) {
+                ctx.db.alive_cells().coordinates().delete((cell.x, cell.y));
+                deaths.push(*cell);
+                continue;
+            }
+        }
+
+        if let Some(alive_cell) = alive_cell.filter(|_| config.dynamic_ownership) {
+            if protected_players.contains(&alive_cell.player_id) {
+                continue;
+            }
+            if let Some(invader) = invader(&counts, alive_cell.player_id, config.capture_threshold as u32) {
+                ctx.db.alive_cells().coordinates().delete((cell.x, cell.y));
+                let color_hex = colors.get(&invader).cloned().unwrap_or_default();
+                ctx.db.alive_cells().insert(AliveCell { player_id: invader, color_hex, weight: 0, ..alive_cell });
+            }
         }
     }
-    Ok(())
-}
\ No newline at end of file
+
+    // an explosion can scatter onto a square that is also born normally
+    births.sort_by_key(|c| (c.x, c.y, c.player_id));
+    births.dedup_by_key(|c| (c.x, c.y));
+
+    // births are applied after deaths so the cap sees this generation's real population,
+    // and in coordinate order so the same births get skipped on every replay
+    let mut at_capacity = false;
+    if config.max_total_cells > 0 {
+        let room = (config.max_total_cells as u64).saturating_sub(ctx.db.alive_cells().count()) as usize;
+        if births.len() > room {
+            births.truncate(room);
+            at_capacity = true;
+        }
+    }
+    let born: Vec<Cell> = births.iter().map(|c| Cell { x: c.x, y: c.y }).collect();
+    for birth in births {
+        ctx.db.alive_cells().insert(birth);
+    }
+
+    // seed weight wears off one generation at a time, captured cells already lost theirs
+    for ((x, y), (player_id, weight)) in weighted {
+        if let Some(alive_cell) = ctx.db.alive_cells().coordinates().filter((x, y)).next().filter(|c| c.player_id == player_id && c.weight == weight) {
+            ctx.db.alive_cells().delete(alive_cell.clone());
+            ctx.db.alive_cells().insert(AliveCell { weight: weight - 1, ..alive_cell });
+        }
+    }
+    GenerationChanges { births: born, deaths, at_capacity }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn l_tromino(op: Transform) -> Vec<(i32, i32)> {
+        let bounds = Bounds::canvas(&default_config());
+        [(10, 10), (10, 11), (11, 11)].iter().map(|&(x, y)| op.apply(&bounds, x, y)).collect()
+    }
+
+    #[test]
+    fn transforms_l_tromino() {
+        assert_eq!(l_tromino(Transform::FlipH), vec![(182, 10), (182, 11), (181, 11)]);
+        assert_eq!(l_tromino(Transform::FlipV), vec![(10, 98), (10, 97), (11, 97)]);
+        assert_eq!(l_tromino(Transform::Rotate90), vec![(140, -32), (139, -32), (139, -31)]);
+        assert_eq!(l_tromino(Transform::Rotate180), vec![(182, 98), (182, 97), (181, 97)]);
+    }
+
+    #[test]
+    fn transforms_undo_themselves() {
+        let bounds = Bounds::canvas(&default_config());
+        for op in [Transform::FlipH, Transform::FlipV, Transform::Rotate180] {
+            let (x, y) = op.apply(&bounds, 10, 11);
+            assert_eq!(op.apply(&bounds, x, y), (10, 11));
+        }
+        let mut p = (10, 11);
+        for _ in 0..4 {
+            p = Transform::Rotate90.apply(&bounds, p.0, p.1);
+        }
+        assert_eq!(p, (10, 11));
+    }
+
+    fn domino_counts(edge_mode: EdgeMode) -> Vec<usize> {
+        let bounds = Bounds::canvas(&default_config());
+        let domino = [(bounds.max_x, 0, 1), (bounds.max_x, 1, 1)];
+        let neighbours = count_neighbours(&domino, &bounds, edge_mode, &[], None);
+        domino.iter().map(|&(x, y, _)| neighbours.by_cell[&Cell { x, y }].len()).collect()
+    }
+
+    #[test]
+    fn domino_on_the_edge_dies_under_kill() {
+        let counts = domino_counts(EdgeMode::Kill);
+        assert_eq!(counts, vec![1, 1]);
+        assert!(counts.iter().all(|&count| !Rule::CONWAY.survives(count)));
+    }
+
+    #[test]
+    fn domino_on_the_edge_survives_its_reflection() {
+        // each half sees its partner, its own mirror image and its partner's mirror image
+        let counts = domino_counts(EdgeMode::Reflect);
+        assert_eq!(counts, vec![3, 3]);
+        assert!(counts.iter().all(|&count| Rule::CONWAY.survives(count)));
+    }
+
+    #[test]
+    fn social_survival_needs_a_friendly_neighbour() {
+        let mut counts = Vec::new();
+        count_owners(&[1, 2, 2], &mut counts);
+        assert!(survives_socially(&counts, 1));
+        assert!(survives_socially(&counts, 2));
+        count_owners(&[2, 2, 3], &mut counts);
+        assert!(!survives_socially(&counts, 1));
+    }
+
+    #[test]
+    fn block_dies_of_old_age_after_max_cell_age() {
+        let max_cell_age = 5;
+        let born_generation = 10;
+        let block = [(10, 10, 1), (10, 11, 1), (11, 10, 1), (11, 11, 1)];
+        let bounds = Bounds::canvas(&default_config());
+        // a still life, every cell sees the other three and the neighbours alone never kill it
+        let by_cell = count_neighbours(&block, &bounds, EdgeMode::Kill, &[], None).by_cell;
+        let survives_rule = block.iter().all(|&(x, y, _)| Rule::CONWAY.survives(by_cell[&Cell { x, y }].len()));
+        assert!(survives_rule);
+
+        // whether the block survives advancing out of a generation, old age being the only way it can die
+        let alive_after = |generation: u64| survives_rule && !too_old(max_cell_age, generation, born_generation);
+        let last = (born_generation..).find(|&generation| !alive_after(generation)).unwrap();
+        // advancing to max_cell_age generations old keeps it, one more kills it
+        assert_eq!(last, born_generation + max_cell_age as u64);
+        assert!(alive_after(last - 1));
+        assert!(!too_old(0, born_generation + 1_000, born_generation));
+    }
+
+    // birth owners on a reflecting edge under B4, where the parents the rule counts
+    // include a mirror image that the Moore neighbourhood doesn't have
+    fn birth_owners_on_reflecting_edge() -> (Option<u32>, Option<u32>) {
+        let bounds = Bounds::canvas(&default_config());
+        let x = bounds.max_x;
+        let alive = [(x, 4, 1), (x - 1, 5, 2), (x - 1, 6, 2)];
+        let by_cell = count_neighbours(&alive, &bounds, EdgeMode::Reflect, &[], None).by_cell;
+        let owners: HashMap<(i32, i32), u32> = alive.iter().map(|&(x, y, player)| ((x, y), player)).collect();
+        let cell = Cell { x, y: 5 };
+        let parents = &by_cell[&cell];
+        assert!(Rule::parse("B4/S23").unwrap().births(parents.len()));
+        let mut counts = Vec::new();
+        count_owners(parents, &mut counts);
+        let majority = birth_owner(ColorMode::Majority, &owners, &HashMap::new(), &cell, &mut counts);
+        count_owners(parents, &mut counts);
+        (majority, birth_owner(ColorMode::Dominance, &owners, &HashMap::new(), &cell, &mut counts))
+    }
+
+    #[test]
+    fn majority_and_dominance_agree_on_plain_parents() {
+        let owners: HashMap<(i32, i32), u32> = [((0, 0), 2), ((1, 0), 2), ((2, 0), 1)].into_iter().collect();
+        let cell = Cell { x: 1, y: 1 };
+        let mut counts = Vec::new();
+        count_owners(&[2, 2, 1], &mut counts);
+        assert_eq!(birth_owner(ColorMode::Majority, &owners, &HashMap::new(), &cell, &mut counts), Some(2));
+        assert_eq!(birth_owner(ColorMode::Dominance, &owners, &HashMap::new(), &cell, &mut counts), Some(2));
+    }
+
+    #[test]
+    fn dominance_ignores_mirrored_parents() {
+        // player 1's cell and its reflection tie with player 2's two cells, the lower id wins the tie,
+        // while the real neighbourhood holds two of player 2's cells against one of player 1's
+        assert_eq!(birth_owners_on_reflecting_edge(), (Some(1), Some(2)));
+    }
+
+    #[test]
+    fn dominance_skips_the_cell_itself() {
+        let owners: HashMap<(i32, i32), u32> = [((0, 0), 3), ((1, 0), 1), ((-1, 0), 2), ((0, 2), 2)].into_iter().collect();
+        let mut counts = Vec::new();
+        assert_eq!(birth_owner(ColorMode::Dominance, &owners, &HashMap::new(), &Cell { x: 0, y: 0 }, &mut counts), Some(1));
+        assert_eq!(counts.len(), 2);
+    }
+
+    #[test]
+    fn invader_needs_the_capture_threshold() {
+        let mut counts = Vec::new();
+        count_owners(&[2, 2, 2, 1], &mut counts);
+        assert_eq!(invader(&counts, 1, 3), Some(2));
+        assert_eq!(invader(&counts, 1, 4), None);
+        // outnumbering the owner is needed too
+        count_owners(&[2, 2, 1, 1], &mut counts);
+        assert_eq!(invader(&counts, 1, 2), None);
+        // a tie between invaders goes to the lower id
+        count_owners(&[3, 3, 2, 2], &mut counts);
+        assert_eq!(invader(&counts, 1, 2), Some(2));
+    }
+
+    struct CountingAlloc;
+
+    thread_local! {
+        static ALLOCATIONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    }
+
+    unsafe impl std::alloc::GlobalAlloc for CountingAlloc {
+        unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+            unsafe { std::alloc::System.alloc(layout) }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+            unsafe { std::alloc::System.dealloc(ptr, layout) }
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAlloc = CountingAlloc;
+
+    fn allocations_during(f: impl FnOnce()) -> usize {
+        let before = ALLOCATIONS.with(|count| count.get());
+        f();
+        ALLOCATIONS.with(|count| count.get()) - before
+    }
+
+    // a 64x64 board, 40% full, shared by 4 players
+    fn busy_board() -> HashMap<(i32, i32), u32> {
+        let mut rng = Rng::new(7);
+        let mut owners = HashMap::new();
+        for x in 0..64 {
+            for y in 0..64 {
+                if rng.chance(40) {
+                    owners.insert((x, y), rng.next_range(1, 5));
+                }
+            }
+        }
+        owners
+    }
+
+    #[test]
+    fn neighbour_tallies_reuse_their_buffer() {
+        let owners = busy_board();
+        let mut counts = Vec::with_capacity(8);
+        let allocations = allocations_during(|| {
+            for x in 0..64 {
+                for y in 0..64 {
+                    std::hint::black_box(tally_neighbours(&owners, x, y, &mut counts));
+                    std::hint::black_box(birth_owner(ColorMode::Dominance, &owners, &HashMap::new(), &Cell { x, y }, &mut counts));
+                }
+            }
+        });
+        assert_eq!(allocations, 0);
+    }
+
+    #[test]
+    fn seed_weight_wins_a_contested_birth() {
+        // player 1 has two parents and player 2 one, but player 2's carries seed weight
+        let owners: HashMap<(i32, i32), u32> = [((0, 0), 1), ((1, 0), 1), ((2, 0), 2)].into_iter().collect();
+        let weighted: HashMap<(i32, i32), (u32, u32)> = [((2, 0), (2, 2))].into_iter().collect();
+        let cell = Cell { x: 1, y: 1 };
+        let mut counts = Vec::new();
+        for color_mode in [ColorMode::Majority, ColorMode::Dominance] {
+            count_owners(&[1, 1, 2], &mut counts);
+            assert_eq!(birth_owner(color_mode, &owners, &HashMap::new(), &cell, &mut counts), Some(1));
+            count_owners(&[1, 1, 2], &mut counts);
+            assert_eq!(birth_owner(color_mode, &owners, &weighted, &cell, &mut counts), Some(2));
+        }
+    }
+
+    #[test]
+    fn overlap_matrix() {
+        let protected: HashSet<u32> = [3].into_iter().collect();
+        assert_eq!(overlap(None, 1, &protected), Overlap::Insert);
+        assert_eq!(overlap(Some(2), 1, &protected), Overlap::Blend);
+        assert_eq!(overlap(Some(1), 1, &protected), Overlap::Skip);
+        assert_eq!(overlap(Some(3), 1, &protected), Overlap::Skip);
+        // neutral blends belong to nobody, so anyone can blend them again
+        assert_eq!(overlap(Some(0), 1, &protected), Overlap::Blend);
+        assert_eq!(blend_owner("placer", 1), 1);
+        assert_eq!(blend_owner("neutral", 1), 0);
+    }
+
+    #[test]
+    fn blends_colors_channel_wise() {
+        assert_eq!(blend_colors("#FF0000", "#0000FF"), "#7F007F");
+        assert_eq!(blend_colors("#102030", "#102030"), "#102030");
+        assert_eq!(blend_colors("#ffffff", "#000001"), "#7F7F80");
+        // a color that doesn't parse gives way to the other one
+        assert_eq!(blend_colors("", "#123456"), "#123456");
+        assert_eq!(blend_colors("#123456", "red"), "#123456");
+    }
+
+    #[test]
+    fn distance_weighted_sums_at_radius_2() {
+        let config = Config {
+            neighborhood_radius: 2,
+            distance_weighting: true,
+            birth_min: 2.0,
+            birth_max: 2.5,
+            survive_min: 3.0,
+            survive_max: 4.0,
+            ..default_config()
+        };
+        let neighborhood = Neighborhood::from_config(&config).unwrap();
+        let alive = [(11, 10, 1), (12, 10, 1), (10, 12, 1)];
+        let sums = count_neighbours(&alive, &Bounds::canvas(&config), EdgeMode::Kill, &[], Some(&neighborhood)).weighted_sums;
+        let sum = |x: i32, y: i32| sums.get(&(x, y)).copied().unwrap_or(0) as f32 / WEIGHT_SCALE as f32;
+
+        // 1/1 + 1/2 + 1/2
+        assert_eq!(sum(10, 10), 2.0);
+        assert!(neighborhood.birth.contains(&sum(10, 10)));
+        // 1/2 + 1/2 + 1/2, too little for a birth
+        assert_eq!(sum(12, 12), 1.5);
+        assert!(!neighborhood.birth.contains(&sum(12, 12)));
+        // 1/1 + 1/2 for the middle cell, too little to survive
+        assert_eq!(sum(11, 10), 1.5);
+        assert!(!neighborhood.survive.contains(&sum(11, 10)));
+        // nothing is in reach of a cell 3 squares away
+        assert_eq!(sum(15, 10), 0.0);
+
+        // 1/1 + 1/2 + 1/1 + 1/1, now enough to survive
+        let alive = [(11, 10, 1), (12, 10, 1), (10, 12, 1), (10, 11, 1), (11, 11, 1)];
+        let sums = count_neighbours(&alive, &Bounds::canvas(&config), EdgeMode::Kill, &[], Some(&neighborhood)).weighted_sums;
+        let sum = sums[&(11, 10)] as f32 / WEIGHT_SCALE as f32;
+        assert_eq!(sum, 3.5);
+        assert!(neighborhood.survive.contains(&sum));
+    }
+
+    #[test]
+    fn unweighted_sums_count_every_cell_in_reach() {
+        let config = Config { neighborhood_radius: 2, ..default_config() };
+        let neighborhood = Neighborhood::from_config(&config).unwrap();
+        let alive = [(11, 10, 1), (12, 10, 1), (10, 12, 1)];
+        let sums = count_neighbours(&alive, &Bounds::canvas(&config), EdgeMode::Kill, &[], Some(&neighborhood)).weighted_sums;
+        assert_eq!(sums[&(10, 10)], 3 * WEIGHT_SCALE);
+        assert!(neighborhood.birth.contains(&3.0));
+    }
+
+    #[test]
+    fn mutation_is_pinned_to_the_seed() {
+        let squares = [(0, 0), (1, 0), (0, 1), (5, -3), (100, 50), (-4, 9)];
+        let mutate = |generation: u64| -> Vec<u32> {
+            let config = Config { rng_seed: 42, generation, mutation_percent: 100, ..default_config() };
+            squares.iter().map(|&(x, y)| mutate_owner(&config, &[1, 2, 3, 4], &Cell { x, y }, 9)).collect()
+        };
+        // changing these numbers breaks replays of recorded games
+        assert_eq!(mutate(7), vec![1, 2, 1, 4, 2, 3]);
+        assert_eq!(mutate(8), vec![4, 1, 1, 1, 1, 4]);
+        // without mutation or players the majority owner stays
+        let config = Config { rng_seed: 42, ..default_config() };
+        assert_eq!(mutate_owner(&config, &[1, 2, 3, 4], &Cell { x: 0, y: 0 }, 9), 9);
+        let config = Config { mutation_percent: 100, ..config };
+        assert_eq!(mutate_owner(&config, &[], &Cell { x: 0, y: 0 }, 9), 9);
+    }
+
+    #[test]
+    fn contagion_only_recolours() {
+        let config = Config { contagion_percent: 100, rng_seed: 3, ..default_config() };
+        // stripes of players 1, 2 and 3
+        let owners: BTreeMap<(i32, i32), u32> = (0..6).flat_map(|x| (0..6).map(move |y| ((x, y), x as u32 % 3 + 1))).collect();
+        let protected: HashSet<u32> = [3].into_iter().collect();
+        let adoptions = contagion_adoptions(&config, &owners, &protected);
+        assert!(!adoptions.is_empty());
+        assert_eq!(adoptions, contagion_adoptions(&config, &owners, &protected));
+
+        let mut after = owners.clone();
+        for &((x, y), adopted) in &adoptions {
+            // the new owner is one of the cell's neighbours
+            assert!((x - 1..=x + 1).any(|nx| (y - 1..=y + 1).any(|ny| (nx, ny) != (x, y) && owners.get(&(nx, ny)) == Some(&adopted))));
+            *after.get_mut(&(x, y)).unwrap() = adopted;
+        }
+        assert!(after.keys().eq(owners.keys()));
+        assert!(owners.iter().filter(|&(_, &owner)| owner == 3).all(|(square, _)| after[square] == 3));
+        assert!(contagion_adoptions(&Config { contagion_percent: 0, ..config }, &owners, &protected).is_empty());
+    }
+
+    #[test]
+    fn smoothing_decides_from_one_snapshot() {
+        let bounds = Bounds::canvas(&default_config());
+        // (0, 0) is empty with 5 live neighbours, one of them the lonely cell at (1, 1),
+        // the cells at x = 2 only keep (1, -1) company
+        let owners: HashMap<(i32, i32), u32> = [((-1, -1), 1), ((0, -1), 1), ((1, -1), 2), ((-1, 0), 1), ((1, 1), 2), ((2, -1), 2), ((2, -2), 2)].into_iter().collect();
+        let changes = smoothing_changes(&owners, &bounds);
+        assert_eq!(changes.lonely, vec![(1, 1)]);
+        // the lonely cell still counts towards the fill, player 1 holds 3 of the 5
+        assert_eq!(changes.fills.into_iter().collect::<Vec<_>>(), vec![((0, 0), 1)]);
+
+        // without it only 4 neighbours are left, too few for a fill
+        let owners: HashMap<(i32, i32), u32> = owners.into_iter().filter(|&(square, _)| square != (1, 1)).collect();
+        let changes = smoothing_changes(&owners, &bounds);
+        assert!(changes.lonely.is_empty());
+        assert!(changes.fills.is_empty());
+    }
+}
@@ -0,0 +1,42 @@
+// Parser for the run length encoded pattern format used by most Life tools (https://conwaylife.com/wiki/Run_Length_Encoded).
+// Returns coordinates of alive cells relative to the top left corner of the pattern.
+pub fn parse(rle: &str, max_cells: usize) -> Result<Vec<(i32, i32)>, String> {
+    let mut cells = Vec::new();
+    let (mut x, mut y) = (0i32, 0i32);
+    let mut run: Option<i32> = None;
+
+    let body = rle.lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#') && !line.starts_with('x'));
+    for line in body {
+        for c in line.chars() {
+            if let Some(digit) = c.to_digit(10) {
+                let count = run.unwrap_or(0).checked_mul(10).and_then(|r| r.checked_add(digit as i32));
+                run = Some(count.ok_or("run length too large")?);
+                continue;
+            }
+            let count = run.take().unwrap_or(1);
+            match c {
+                'b' | '.' => x = x.checked_add(count).ok_or("pattern too wide")?,
+                '$' => {
+                    y = y.checked_add(count).ok_or("pattern too tall")?;
+                    x = 0;
+                }
+                '!' => return Ok(cells),
+                c if c.is_ascii_alphabetic() => {
+                    // multi-state formats use other letters, they all count as alive here
+                    if cells.len() + count as usize > max_cells {
+                        return Err(format!("pattern has more than {} cells", max_cells));
+                    }
+                    for _ in 0..count {
+                        cells.push((x, y));
+                        x = x.checked_add(1).ok_or("pattern too wide")?;
+                    }
+                }
+                c if c.is_whitespace() => {}
+                c => return Err(format!("unexpected character in pattern: {}", c)),
+            }
+        }
+    }
+    Ok(cells)
+}
@@ -0,0 +1,46 @@
+// Small seedable PRNG (xorshift64*). Reducers have to be deterministic,
+// so randomness always comes from an explicit seed instead of the clock.
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // splitmix64 spreads similar seeds apart and keeps the state away from zero
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        Rng { state: if z == 0 { 0x9E37_79B9_7F4A_7C15 } else { z } }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    // uniform in lo..hi, rejection sampling keeps small ranges free of modulo bias
+    pub fn next_range(&mut self, lo: u32, hi: u32) -> u32 {
+        if hi <= lo {
+            return lo;
+        }
+        let span = hi - lo;
+        let zone = u32::MAX - (u32::MAX - span + 1) % span;
+        loop {
+            let v = self.next_u32();
+            if v <= zone {
+                return lo + v % span;
+            }
+        }
+    }
+
+    pub fn chance(&mut self, percent: u8) -> bool {
+        self.next_range(0, 100) < percent as u32
+    }
+}